@@ -0,0 +1,104 @@
+use clap::Parser;
+use tokio_serial::{DataBits, FlowControl, Parity, SerialPortType, StopBits};
+
+use crate::common::config::{parse_data_bits, parse_flow_control, parse_parity, parse_stop_bits};
+use crate::common::receive::Capacity;
+
+/// Serial port terminal. Opens PORT directly when given, otherwise starts on the port picker.
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Serial port path, e.g. /dev/ttyUSB0 or COM3
+    pub port: Option<String>,
+    #[arg(short, long)]
+    pub baud_rate: Option<u32>,
+    /// 5, 6, 7 or 8
+    #[arg(long, value_parser = data_bits_arg)]
+    pub data_bits: Option<DataBits>,
+    /// 1 or 2
+    #[arg(long, value_parser = stop_bits_arg)]
+    pub stop_bits: Option<StopBits>,
+    /// none, odd or even
+    #[arg(long, value_parser = parity_arg)]
+    pub parity: Option<Parity>,
+    /// none, software or hardware
+    #[arg(long, value_parser = flow_control_arg)]
+    pub flow: Option<FlowControl>,
+    /// Receive history to keep, in lines (10000) or megabytes (64MB)
+    #[arg(long, value_parser = history_arg)]
+    pub history: Option<Capacity>,
+    /// QA mode: milliseconds of silence that end a response
+    #[arg(long)]
+    pub qa_idle: Option<u64>,
+    /// QA mode: milliseconds to wait for a response to complete
+    #[arg(long)]
+    pub qa_timeout: Option<u64>,
+    /// Milliseconds of silence that end a frame in idle framing, 3.5 character times by default
+    #[arg(long, value_parser = frame_gap_arg)]
+    pub frame_gap: Option<f64>,
+    /// Load a saved profile, other options override its settings
+    #[arg(short, long)]
+    pub profile: Option<String>,
+    /// Print the available ports and exit
+    #[arg(short, long)]
+    pub list: bool,
+}
+
+fn data_bits_arg(text: &str) -> Result<DataBits, String> {
+    text.parse()
+        .ok()
+        .and_then(parse_data_bits)
+        .ok_or(format!("{text}: expected 5, 6, 7 or 8"))
+}
+
+fn stop_bits_arg(text: &str) -> Result<StopBits, String> {
+    text.parse()
+        .ok()
+        .and_then(parse_stop_bits)
+        .ok_or(format!("{text}: expected 1 or 2"))
+}
+
+fn parity_arg(text: &str) -> Result<Parity, String> {
+    parse_parity(text).ok_or(format!("{text}: expected none, odd or even"))
+}
+
+fn flow_control_arg(text: &str) -> Result<FlowControl, String> {
+    parse_flow_control(text).ok_or(format!("{text}: expected none, software or hardware"))
+}
+
+fn history_arg(text: &str) -> Result<Capacity, String> {
+    Capacity::parse(text).ok_or(format!("{text}: expected a line count or a size like 64MB"))
+}
+
+fn frame_gap_arg(text: &str) -> Result<f64, String> {
+    text.parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite() && *v > 0.0)
+        .ok_or(format!("{text}: expected a positive number of milliseconds"))
+}
+
+/// Print one line per available port, with the USB metadata when there is any.
+pub fn list_ports() -> tokio_serial::Result<()> {
+    for port in tokio_serial::available_ports()? {
+        let detail = match port.port_type {
+            SerialPortType::UsbPort(usb) => {
+                let mut text = format!("usb {0:04x}:{1:04x}", usb.vid, usb.pid);
+                if let Some(v) = usb.manufacturer {
+                    text.push_str(&format!(" manufacturer=\"{v}\""));
+                }
+                if let Some(v) = usb.product {
+                    text.push_str(&format!(" product=\"{v}\""));
+                }
+                if let Some(v) = usb.serial_number {
+                    text.push_str(&format!(" serial=\"{v}\""));
+                }
+                text
+            }
+            SerialPortType::PciPort => String::from("pci"),
+            SerialPortType::BluetoothPort => String::from("bluetooth"),
+            SerialPortType::Unknown => String::from("unknown"),
+        };
+        println!("{0}\t{detail}", port.port_name);
+    }
+    Ok(())
+}
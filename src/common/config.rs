@@ -0,0 +1,185 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio_serial::{DataBits, FlowControl, Parity, StopBits};
+
+use crate::common::receive::DEFAULT_CAPACITY;
+
+/// Saved connection settings, stored as plain values so the file stays hand editable.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Profile {
+    pub port: String,
+    /// USB identity of the port, used to find it again under a different path.
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: u8,
+    pub parity: String,
+    pub flow_control: String,
+    pub hex_mode: bool,
+    pub line_ending: String,
+    /// Receive history kept, `10000 lines` or `64MB`.
+    pub history: String,
+    pub encoding: String,
+    /// How bytes that do not decode are shown, `replace` or `hex`.
+    pub invalid: String,
+    /// Where received lines are split, `lf`, `cr`, `crlf`, `custom:HH` or `idle`.
+    pub delimiter: String,
+    pub timestamp: String,
+    pub echo: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            port: String::new(),
+            vid: None,
+            pid: None,
+            serial_number: None,
+            baud_rate: 115200,
+            data_bits: 8,
+            stop_bits: 1,
+            parity: String::from("none"),
+            flow_control: String::from("none"),
+            hex_mode: false,
+            line_ending: String::from("none"),
+            history: DEFAULT_CAPACITY.to_string(),
+            encoding: String::from("utf-8"),
+            invalid: String::from("replace"),
+            delimiter: String::from("lf"),
+            timestamp: String::from("off"),
+            echo: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub last_profile: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Self {
+            last_profile: None,
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    /// Read the config file, a missing file is an empty config.
+    pub fn load() -> Result<Self, String> {
+        load_toml("config.toml")
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_toml("config.toml", self)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/serial_tool/<name>` or the platform equivalent.
+fn config_path(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|v| v.join("serial_tool").join(name))
+}
+
+/// Read a file from the config directory, a missing file gives the default value.
+pub fn load_toml<T: DeserializeOwned + Default>(name: &str) -> Result<T, String> {
+    let Some(path) = config_path(name) else {
+        return Ok(T::default());
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("{0}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("{0}: {e}", path.display())),
+    }
+}
+
+/// Write `value` to a file in the config directory, creating the directory when needed.
+pub fn save_toml<T: Serialize>(name: &str, value: &T) -> Result<(), String> {
+    let Some(path) = config_path(name) else {
+        return Err(String::from("no config directory"));
+    };
+    let text = toml::to_string_pretty(value).map_err(|e| e.to_string())?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{0}: {e}", dir.display()))?;
+    }
+    fs::write(&path, text).map_err(|e| format!("{0}: {e}", path.display()))
+}
+
+pub fn parse_data_bits(bits: u8) -> Option<DataBits> {
+    match bits {
+        5 => Some(DataBits::Five),
+        6 => Some(DataBits::Six),
+        7 => Some(DataBits::Seven),
+        8 => Some(DataBits::Eight),
+        _ => None,
+    }
+}
+
+pub fn data_bits_value(bits: DataBits) -> u8 {
+    match bits {
+        DataBits::Five => 5,
+        DataBits::Six => 6,
+        DataBits::Seven => 7,
+        DataBits::Eight => 8,
+    }
+}
+
+pub fn parse_stop_bits(bits: u8) -> Option<StopBits> {
+    match bits {
+        1 => Some(StopBits::One),
+        2 => Some(StopBits::Two),
+        _ => None,
+    }
+}
+
+pub fn stop_bits_value(bits: StopBits) -> u8 {
+    match bits {
+        StopBits::One => 1,
+        StopBits::Two => 2,
+    }
+}
+
+pub fn parse_parity(text: &str) -> Option<Parity> {
+    match text.to_lowercase().as_str() {
+        "none" | "n" => Some(Parity::None),
+        "odd" | "o" => Some(Parity::Odd),
+        "even" | "e" => Some(Parity::Even),
+        _ => None,
+    }
+}
+
+pub fn parity_name(parity: Parity) -> &'static str {
+    match parity {
+        Parity::None => "none",
+        Parity::Odd => "odd",
+        Parity::Even => "even",
+    }
+}
+
+pub fn parse_flow_control(text: &str) -> Option<FlowControl> {
+    match text.to_lowercase().as_str() {
+        "none" => Some(FlowControl::None),
+        "software" | "xonxoff" => Some(FlowControl::Software),
+        "hardware" | "rtscts" => Some(FlowControl::Hardware),
+        _ => None,
+    }
+}
+
+pub fn flow_control_name(flow_control: FlowControl) -> &'static str {
+    match flow_control {
+        FlowControl::None => "none",
+        FlowControl::Software => "software",
+        FlowControl::Hardware => "hardware",
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Window over which the rolling rate is averaged.
+const WINDOW: Duration = Duration::from_secs(1);
+
+pub struct Counter {
+    total: usize,
+    samples: VecDeque<(Instant, usize)>,
+    rate: f64,
+    peak: f64,
+}
+
+impl Counter {
+    pub const fn new() -> Self {
+        Self {
+            total: 0,
+            samples: VecDeque::new(),
+            rate: 0.0,
+            peak: 0.0,
+        }
+    }
+
+    pub fn add(&mut self, size: usize) {
+        self.total += size;
+        self.samples.push_back((Instant::now(), size));
+    }
+
+    /// Drop samples that left the window and recompute the rate and peak.
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        while let Some((time, _)) = self.samples.front() {
+            if now.duration_since(*time) <= WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        let bytes: usize = self.samples.iter().map(|(_, size)| size).sum();
+        self.rate = bytes as f64 / WINDOW.as_secs_f64();
+        if self.rate > self.peak {
+            self.peak = self.rate;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    pub fn get_rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn get_peak(&self) -> f64 {
+        self.peak
+    }
+}
+
+pub fn format_bytes(size: f64) -> String {
+    if size >= 1024.0 * 1024.0 {
+        format!("{:.1}MB", size / 1024.0 / 1024.0)
+    } else if size >= 1024.0 {
+        format!("{:.1}KB", size / 1024.0)
+    } else {
+        format!("{size:.0}B")
+    }
+}
@@ -0,0 +1,210 @@
+use strum::{Display, FromRepr};
+
+#[derive(PartialEq, Clone, Copy, Display, FromRepr)]
+pub enum Encoding {
+    #[strum(to_string = "UTF-8")]
+    Utf8,
+    #[strum(to_string = "GBK")]
+    Gbk,
+    #[strum(to_string = "Latin-1")]
+    Latin1,
+}
+
+impl Encoding {
+    pub fn next(self) -> Self {
+        let next_index = (self as usize).saturating_add(1);
+        Self::from_repr(next_index).unwrap_or(Encoding::Utf8)
+    }
+
+    /// Parse the name stored in a profile.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Encoding::Utf8),
+            "gbk" => Some(Encoding::Gbk),
+            "latin-1" | "latin1" => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Gbk => "gbk",
+            Encoding::Latin1 => "latin-1",
+        }
+    }
+
+    /// Length of the sequence starting with `bytes[0]`, `None` when more bytes are needed to tell.
+    fn sequence_len(self, bytes: &[u8]) -> Option<usize> {
+        match self {
+            Encoding::Utf8 => Some(match bytes[0] {
+                0xc2..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf4 => 4,
+                _ => 1,
+            }),
+            Encoding::Gbk => match bytes[0] {
+                // four byte GB18030 sequences have a digit as their second byte
+                0x81..=0xfe => bytes.get(1).map(|v| if v.is_ascii_digit() { 4 } else { 2 }),
+                _ => Some(1),
+            },
+            Encoding::Latin1 => Some(1),
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Encoding::Utf8 => std::str::from_utf8(bytes).ok().map(String::from),
+            Encoding::Gbk => encoding_rs::GBK
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|v| v.into_owned()),
+            Encoding::Latin1 => Some(bytes.iter().map(|v| *v as char).collect()),
+        }
+    }
+}
+
+/// How bytes that do not decode are shown.
+#[derive(PartialEq, Clone, Copy, Display)]
+pub enum Invalid {
+    #[strum(to_string = "\u{fffd}")]
+    Replace,
+    #[strum(to_string = "\\xHH")]
+    Hex,
+}
+
+impl Invalid {
+    /// Parse the name stored in a profile.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "replace" => Some(Invalid::Replace),
+            "hex" => Some(Invalid::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Invalid::Replace => "replace",
+            Invalid::Hex => "hex",
+        }
+    }
+}
+
+/// Streaming decoder that keeps an incomplete trailing sequence until the next chunk arrives.
+pub struct Decoder {
+    encoding: Encoding,
+    invalid: Invalid,
+    pending: Vec<u8>,
+}
+
+impl Decoder {
+    pub const fn new(encoding: Encoding, invalid: Invalid) -> Self {
+        Self {
+            encoding,
+            invalid,
+            pending: vec![],
+        }
+    }
+
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut text = String::new();
+        let mut i = 0;
+        while i < self.pending.len() {
+            let rest = &self.pending[i..];
+            let Some(len) = self.encoding.sequence_len(rest) else {
+                break;
+            };
+            if rest.len() < len {
+                // a valid prefix waits for the rest of the sequence
+                if self.encoding.decode(rest).is_none() && self.is_prefix(rest) {
+                    break;
+                }
+            }
+            match rest.get(..len).and_then(|v| self.encoding.decode(v)) {
+                Some(v) => {
+                    text.push_str(&v);
+                    i += len;
+                }
+                None => {
+                    // skip one byte so decoding can resynchronise on the next one
+                    self.push_invalid(&mut text, rest[0]);
+                    i += 1;
+                }
+            }
+        }
+        self.pending.drain(..i);
+        text
+    }
+
+    /// Render whatever is still pending as invalid bytes, once no more data will follow.
+    pub fn flush(&mut self) -> String {
+        let mut text = String::new();
+        for byte in std::mem::take(&mut self.pending) {
+            self.push_invalid(&mut text, byte);
+        }
+        text
+    }
+
+    /// Whether `bytes` can still grow into a valid sequence.
+    fn is_prefix(&self, bytes: &[u8]) -> bool {
+        match self.encoding {
+            Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                Err(e) => e.error_len().is_none(),
+                Ok(_) => false,
+            },
+            _ => true,
+        }
+    }
+
+    fn push_invalid(&self, text: &mut String, byte: u8) {
+        match self.invalid {
+            Invalid::Replace => text.push('\u{fffd}'),
+            Invalid::Hex => text.push_str(&format!("\\x{byte:02X}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode `bytes` in two chunks split at every position.
+    fn split_everywhere(encoding: Encoding, bytes: &[u8], expected: &str) {
+        for i in 0..=bytes.len() {
+            let mut decoder = Decoder::new(encoding, Invalid::Hex);
+            let mut text = decoder.decode(&bytes[..i]);
+            text.push_str(&decoder.decode(&bytes[i..]));
+            assert_eq!(text, expected, "split at {i}");
+            assert_eq!(decoder.flush(), "", "split at {i}");
+        }
+    }
+
+    #[test]
+    fn utf8_across_chunks() {
+        let text = "a中€😀é";
+        split_everywhere(Encoding::Utf8, text.as_bytes(), text);
+    }
+
+    #[test]
+    fn gbk_across_chunks() {
+        split_everywhere(Encoding::Gbk, b"a\xd6\xd0\xce\xc4b", "a中文b");
+    }
+
+    #[test]
+    fn partial_sequence_waits() {
+        let mut decoder = Decoder::new(Encoding::Utf8, Invalid::Hex);
+        assert_eq!(decoder.decode(b"x\xe4"), "x");
+        assert_eq!(decoder.decode(b"\xb8"), "");
+        assert_eq!(decoder.decode(b"\xad!"), "中!");
+    }
+
+    #[test]
+    fn invalid_bytes() {
+        let mut decoder = Decoder::new(Encoding::Utf8, Invalid::Hex);
+        assert_eq!(decoder.decode(b"a\xffb\xe4x"), "a\\xFFb\\xE4x");
+        let mut decoder = Decoder::new(Encoding::Utf8, Invalid::Replace);
+        assert_eq!(decoder.decode(b"a\xe4\xb8"), "a");
+        assert_eq!(decoder.flush(), "\u{fffd}\u{fffd}");
+    }
+}
@@ -0,0 +1,101 @@
+/// Decode C-style escapes in `text`: `\r \n \t \\ \0 \e \xHH` and `\u{...}`.
+/// On failure returns the char index of the offending backslash and a description.
+pub fn parse_escape(text: &str) -> Result<Vec<u8>, (usize, String)> {
+    let mut bytes = vec![];
+    let mut chars = text.chars().enumerate().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next().map(|(_, v)| v) {
+            Some('r') => bytes.push(b'\r'),
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some('\\') => bytes.push(b'\\'),
+            Some('0') => bytes.push(0),
+            Some('e') => bytes.push(0x1b),
+            Some('x') => {
+                let digits: String = (0..2).filter_map(|_| chars.next().map(|(_, v)| v)).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(v) if digits.len() == 2 => bytes.push(v),
+                    _ => return Err((i, String::from("\\x needs two hex digits"))),
+                }
+            }
+            Some('u') => {
+                if chars.next().map(|(_, v)| v) != Some('{') {
+                    return Err((i, String::from("\\u needs {...}")));
+                }
+                let mut digits = String::new();
+                loop {
+                    match chars.next().map(|(_, v)| v) {
+                        Some('}') => break,
+                        Some(v) => digits.push(v),
+                        None => return Err((i, String::from("unterminated \\u{"))),
+                    }
+                }
+                let c = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() <= 6)
+                    .and_then(char::from_u32);
+                match c {
+                    Some(c) => {
+                        let mut buf = [0; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    }
+                    None => return Err((i, format!("\\u{{{digits}}} is not a character"))),
+                }
+            }
+            Some(v) => return Err((i, format!("unknown escape \\{v}"))),
+            None => return Err((i, String::from("trailing \\"))),
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> usize {
+        parse_escape(text).unwrap_err().0
+    }
+
+    #[test]
+    fn plain_text_is_utf8() {
+        assert_eq!(parse_escape("AT 中").unwrap(), "AT 中".as_bytes());
+    }
+
+    #[test]
+    fn each_escape() {
+        assert_eq!(
+            parse_escape(r"\r\n\t\\\0\e\x7F\xaa").unwrap(),
+            [b'\r', b'\n', b'\t', b'\\', 0, 0x1b, 0x7f, 0xaa]
+        );
+        assert_eq!(parse_escape(r"a\u{41}\u{4e2d}\u{1F600}").unwrap(), "aA中😀".as_bytes());
+    }
+
+    #[test]
+    fn short_hex_escape() {
+        assert_eq!(error(r"AT\x4"), 2);
+        assert_eq!(error(r"\x"), 0);
+        assert_eq!(error(r"\x4G"), 0);
+    }
+
+    #[test]
+    fn bad_unicode_escape() {
+        assert_eq!(error(r"\u{}"), 0);
+        assert_eq!(error(r"a\u{110000}"), 1);
+        assert_eq!(error(r"\u{D800}"), 0);
+        assert_eq!(error(r"\u{0000041}"), 0);
+        assert_eq!(error(r"\u41"), 0);
+        assert_eq!(parse_escape(r"ab\u{41").unwrap_err(), (2, String::from("unterminated \\u{")));
+    }
+
+    #[test]
+    fn unknown_and_trailing_backslash() {
+        assert_eq!(error(r"\q"), 0);
+        assert_eq!(parse_escape("AT\\").unwrap_err(), (2, String::from("trailing \\")));
+    }
+}
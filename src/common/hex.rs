@@ -0,0 +1,131 @@
+/// Bytes shown on one hexdump row.
+pub const HEXDUMP_WIDTH: usize = 16;
+
+/// Format one hexdump row: offset, up to 16 hex bytes split in two groups and an ASCII gutter.
+pub fn hexdump_line(offset: usize, bytes: &[u8]) -> String {
+    let mut text = format!("{offset:08x} ");
+    for i in 0..HEXDUMP_WIDTH {
+        if i % 8 == 0 {
+            text.push(' ');
+        }
+        match bytes.get(i) {
+            Some(v) => text.push_str(&format!("{v:02x} ")),
+            None => text.push_str("   "),
+        }
+    }
+    text.push_str(" |");
+    for v in bytes.iter() {
+        text.push(printable(*v));
+    }
+    text.push('|');
+    text
+}
+
+/// Bytes as lowercase hex pairs joined by `separator`, e.g. `0d 0a`.
+pub fn hex_string(bytes: &[u8], separator: &str) -> String {
+    bytes.iter().map(|v| format!("{v:02x}")).collect::<Vec<_>>().join(separator)
+}
+
+/// The byte as an ASCII character, or a dot when it is not printable.
+pub fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        byte as char
+    } else {
+        '.'
+    }
+}
+
+/// Parse hex typed as `AA 55 01 0F`, `0xAA,0x55` or `AA5501`.
+/// Returns the decoded bytes and the char indices of the characters that are not valid,
+/// the input is only usable when the second list is empty.
+pub fn parse_hex(text: &str) -> (Vec<u8>, Vec<usize>) {
+    let mut bytes = vec![];
+    let mut errors = vec![];
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if is_separator(chars[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && !is_separator(chars[i]) {
+            i += 1;
+        }
+        let mut token = start;
+        if i - start > 2 && chars[start] == '0' && matches!(chars[start + 1], 'x' | 'X') {
+            token += 2;
+        }
+
+        let digits: Vec<(usize, u8)> = (token..i)
+            .filter_map(|j| match chars[j].to_digit(16) {
+                Some(v) => Some((j, v as u8)),
+                None => {
+                    errors.push(j);
+                    None
+                }
+            })
+            .collect();
+        if digits.len() == 1 {
+            bytes.push(digits[0].1);
+            continue;
+        }
+        for pair in digits.chunks(2) {
+            match pair {
+                [(_, high), (_, low)] => bytes.push(high << 4 | low),
+                // a dangling nibble at the end of a run
+                [(j, _)] => errors.push(*j),
+                _ => {}
+            }
+        }
+    }
+    errors.sort();
+    (bytes, errors)
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || c == ',' || c == ';'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaced_bytes() {
+        assert_eq!(parse_hex("AA 55 01 0F"), (vec![0xaa, 0x55, 0x01, 0x0f], vec![]));
+    }
+
+    #[test]
+    fn prefixed_bytes() {
+        assert_eq!(parse_hex("0xAA,0x55"), (vec![0xaa, 0x55], vec![]));
+        assert_eq!(parse_hex("0Xaa; 0x5"), (vec![0xaa, 0x05], vec![]));
+    }
+
+    #[test]
+    fn packed_bytes() {
+        assert_eq!(parse_hex("AA5501"), (vec![0xaa, 0x55, 0x01], vec![]));
+    }
+
+    #[test]
+    fn single_digit_token() {
+        assert_eq!(parse_hex("A 5 ff"), (vec![0x0a, 0x05, 0xff], vec![]));
+    }
+
+    #[test]
+    fn dangling_nibble() {
+        assert_eq!(parse_hex("AA5").1, [2]);
+        assert_eq!(parse_hex("01 AA5 02").1, [5]);
+    }
+
+    #[test]
+    fn bare_prefix() {
+        assert_eq!(parse_hex("0x").1, [1]);
+        assert_eq!(parse_hex("AA 0x").1, [4]);
+    }
+
+    #[test]
+    fn invalid_digit() {
+        assert_eq!(parse_hex("AG 中1").1, [1, 3]);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::config::{load_toml, save_toml};
+
+/// Sends kept per port, older ones are dropped.
+const MAX_ENTRIES: usize = 1000;
+
+/// Previously sent input lines, walked with Up/Down and searched with Ctrl-R.
+pub struct History {
+    entries: Vec<String>,
+    /// Entry shown in the input, `None` while editing a new line.
+    position: Option<usize>,
+    /// The line being edited before walking into the history.
+    draft: String,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        Self {
+            entries: vec![],
+            position: None,
+            draft: String::new(),
+        }
+    }
+
+    pub fn get_entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Replace the entries, keeping only the newest `MAX_ENTRIES` of them.
+    pub fn set_entries(&mut self, mut entries: Vec<String>) {
+        entries.drain(..entries.len().saturating_sub(MAX_ENTRIES));
+        self.entries = entries;
+        self.position = None;
+    }
+
+    /// Record a sent line, a repeat of the last one is not stored twice.
+    pub fn push(&mut self, text: &str) {
+        self.position = None;
+        if text.is_empty() || self.entries.last().is_some_and(|v| v == text) {
+            return;
+        }
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.drain(..=self.entries.len() - MAX_ENTRIES);
+        }
+        self.entries.push(text.to_string());
+    }
+
+    /// The entry before the one shown, `current` is kept to come back to.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            Some(0) => return None,
+            Some(i) => i - 1,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// The entry after the one shown, or the draft once past the newest.
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            Some(&self.entries[position + 1])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Index of the newest entry before `before` that contains `query`.
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|v| v.contains(query))
+    }
+}
+
+/// Send history of every port, stored next to the config file.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFile {
+    pub ports: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for HistoryFile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HistoryFile {
+    pub const fn new() -> Self {
+        Self {
+            ports: BTreeMap::new(),
+        }
+    }
+
+    /// Read the history file, a missing file is an empty history.
+    pub fn load() -> Result<Self, String> {
+        load_toml("history.toml")
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        save_toml("history.toml", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        let mut history = History::new();
+        history.set_entries(entries.iter().map(|v| v.to_string()).collect());
+        history
+    }
+
+    #[test]
+    fn push_skips_repeats_and_empty() {
+        let mut v = history(&[]);
+        v.push("AT");
+        v.push("AT");
+        v.push("");
+        v.push("ATI");
+        v.push("AT");
+        assert_eq!(v.get_entries(), ["AT", "ATI", "AT"]);
+    }
+
+    #[test]
+    fn entries_are_capped() {
+        let entries: Vec<String> = (0..MAX_ENTRIES + 5).map(|v| v.to_string()).collect();
+        let mut v = History::new();
+        v.set_entries(entries);
+        assert_eq!(v.get_entries().len(), MAX_ENTRIES);
+        assert_eq!(v.get_entries()[0], "5");
+        v.push("new");
+        assert_eq!(v.get_entries().len(), MAX_ENTRIES);
+        assert_eq!(v.get_entries()[0], "6");
+        assert_eq!(v.get_entries()[MAX_ENTRIES - 1], "new");
+    }
+
+    #[test]
+    fn previous_and_next_keep_the_draft() {
+        let mut v = history(&["one", "two"]);
+        assert_eq!(v.next(), None);
+        assert_eq!(v.previous("dra"), Some("two"));
+        assert_eq!(v.previous("two"), Some("one"));
+        assert_eq!(v.previous("one"), None);
+        assert_eq!(v.next(), Some("two"));
+        assert_eq!(v.next(), Some("dra"));
+        assert_eq!(v.next(), None);
+        assert_eq!(history(&[]).previous("x"), None);
+    }
+
+    #[test]
+    fn push_leaves_the_history() {
+        let mut v = history(&["one", "two"]);
+        v.previous("");
+        v.push("three");
+        assert_eq!(v.previous(""), Some("three"));
+    }
+
+    #[test]
+    fn search_finds_newest_match_before() {
+        let v = history(&["AT+CSQ", "ATI", "AT+CSQ?", "ATZ"]);
+        assert_eq!(v.search("CSQ", 4), Some(2));
+        assert_eq!(v.search("CSQ", 2), Some(0));
+        assert_eq!(v.search("CSQ", 0), None);
+        assert_eq!(v.search("x", 4), None);
+        assert_eq!(v.search("", 100), Some(3));
+    }
+}
@@ -1,6 +1,28 @@
+use std::{cell::Cell, ops::Range};
+
+use unicode_width::UnicodeWidthChar;
+
 pub struct Input {
     buf: String,
     character_index: usize,
+    /// Text removed by the last kill command, put back with `yank`.
+    killed: String,
+    /// First display column shown, kept between draws so the view only moves when it has to.
+    scroll: Cell<usize>,
+}
+
+/// The part of the input that fits the width it is drawn in.
+pub struct InputView {
+    /// Chars shown.
+    pub range: Range<usize>,
+    /// Blank columns before the first char, left by a wide char cut at the edge.
+    pub pad: usize,
+    /// Cursor column, counted from the first column after the left marker.
+    pub cursor: usize,
+    /// Text is hidden on the left.
+    pub left: bool,
+    /// Text is hidden on the right.
+    pub right: bool,
 }
 
 impl Input {
@@ -8,6 +30,71 @@ impl Input {
         Self {
             buf: String::new(),
             character_index: 0,
+            killed: String::new(),
+            scroll: Cell::new(0),
+        }
+    }
+
+    /// Display width of the chars before the cursor.
+    pub fn cursor_width(&self) -> usize {
+        self.buf.chars().take(self.character_index).map(char_width).sum()
+    }
+
+    /// Scroll the line so the cursor stays inside `width` columns, markers included.
+    pub fn view(&self, width: usize) -> InputView {
+        let widths: Vec<usize> = self.buf.chars().map(char_width).collect();
+        let cursor = self.cursor_width();
+        // the cursor may sit one column past the text
+        let total = widths.iter().sum::<usize>() + 1;
+        let width = width.max(3);
+        let inner = |offset: usize| {
+            let left = usize::from(offset > 0);
+            let right = usize::from(total > offset + width - left);
+            width - left - right
+        };
+        let mut offset = self.scroll.get().min(cursor);
+        // no blank space at the end while text is hidden on the left
+        if offset > 0 && total < offset + width - 1 {
+            offset = total.saturating_sub(width - 1);
+        }
+        if cursor >= offset + inner(offset) {
+            // cursor at the right edge, before the right marker if there is one
+            offset = cursor + 2 - width;
+            if total < offset + width {
+                offset = total - (width - 1);
+            }
+        }
+        self.scroll.set(offset);
+
+        let left = offset > 0;
+        let right = total > offset + width - usize::from(left);
+        let inner = inner(offset);
+        let mut column = 0;
+        let mut start = widths.len();
+        let mut pad = 0;
+        for (i, w) in widths.iter().enumerate() {
+            if column >= offset {
+                start = i;
+                break;
+            }
+            column += w;
+            // a wide char cut by the left edge is shown as blanks
+            if column > offset {
+                pad = column - offset;
+            }
+        }
+        let mut end = start;
+        let mut used = pad;
+        while end < widths.len() && used + widths[end] <= inner {
+            used += widths[end];
+            end += 1;
+        }
+        InputView {
+            range: start..end,
+            pad,
+            cursor: cursor - offset,
+            left,
+            right,
         }
     }
 
@@ -19,6 +106,11 @@ impl Input {
         &self.buf
     }
 
+    pub fn set_string(&mut self, text: &str) {
+        self.buf = text.to_string();
+        self.character_index = self.buf.chars().count();
+    }
+
     pub fn move_cursor_left(&mut self) {
         let cursor_moved_left = self.character_index.saturating_sub(1);
         self.character_index = self.clamp_cursor(cursor_moved_left);
@@ -29,6 +121,45 @@ impl Input {
         self.character_index = self.clamp_cursor(cursor_moved_right);
     }
 
+    pub fn move_cursor_home(&mut self) {
+        self.character_index = 0;
+    }
+
+    pub fn move_cursor_end(&mut self) {
+        self.character_index = self.buf.chars().count();
+    }
+
+    /// Move to the start of the word before the cursor.
+    pub fn move_word_left(&mut self) {
+        self.character_index = self.word_start();
+    }
+
+    /// Move past the end of the word after the cursor.
+    pub fn move_word_right(&mut self) {
+        let chars: Vec<char> = self.buf.chars().collect();
+        let mut index = self.character_index;
+        while index < chars.len() && chars[index].is_whitespace() {
+            index += 1;
+        }
+        while index < chars.len() && !chars[index].is_whitespace() {
+            index += 1;
+        }
+        self.character_index = index;
+    }
+
+    /// Char index of the start of the word before the cursor, skipping whitespace first.
+    fn word_start(&self) -> usize {
+        let chars: Vec<char> = self.buf.chars().collect();
+        let mut index = self.character_index;
+        while index > 0 && chars[index - 1].is_whitespace() {
+            index -= 1;
+        }
+        while index > 0 && !chars[index - 1].is_whitespace() {
+            index -= 1;
+        }
+        index
+    }
+
     pub fn enter_char(&mut self, new_char: char) {
         let index = self.byte_index();
         self.buf.insert(index, new_char);
@@ -36,11 +167,7 @@ impl Input {
     }
 
     pub fn byte_index(&mut self) -> usize {
-        self.buf
-            .char_indices()
-            .map(|(i, _)| i)
-            .nth(self.character_index)
-            .unwrap_or(self.buf.len())
+        self.char_to_byte(self.character_index)
     }
 
     pub fn delete_char(&mut self) {
@@ -65,6 +192,63 @@ impl Input {
         }
     }
 
+    /// Delete the character under the cursor.
+    pub fn delete_char_forward(&mut self) {
+        if self.character_index < self.buf.chars().count() {
+            self.character_index += 1;
+            self.delete_char();
+        }
+    }
+
+    /// Insert `text` at the cursor, as from a paste.
+    pub fn insert_str(&mut self, text: &str) {
+        let index = self.byte_index();
+        self.buf.insert_str(index, text);
+        self.character_index += text.chars().count();
+    }
+
+    /// Insert pasted text at the cursor as a single line, see `paste_line`.
+    pub fn paste(&mut self, text: &str, keep_tab: bool) {
+        self.insert_str(&paste_line(text, keep_tab));
+    }
+
+    /// Remove the chars between two char indices into the kill buffer.
+    fn kill(&mut self, start: usize, end: usize) {
+        let start_byte = self.char_to_byte(start);
+        let end_byte = self.char_to_byte(end);
+        self.killed = self.buf.drain(start_byte..end_byte).collect();
+        self.character_index = start;
+    }
+
+    /// Ctrl-W: kill the word before the cursor.
+    pub fn kill_word_back(&mut self) {
+        self.kill(self.word_start(), self.character_index);
+    }
+
+    /// Ctrl-U: kill from the start of the line to the cursor.
+    pub fn kill_to_start(&mut self) {
+        self.kill(0, self.character_index);
+    }
+
+    /// Ctrl-K: kill from the cursor to the end of the line.
+    pub fn kill_to_end(&mut self) {
+        self.kill(self.character_index, self.buf.chars().count());
+    }
+
+    /// Ctrl-Y: insert the last killed text at the cursor.
+    pub fn yank(&mut self) {
+        let text = self.killed.clone();
+        self.insert_str(&text);
+    }
+
+    fn char_to_byte(&self, index: usize) -> usize {
+        self.buf
+            .char_indices()
+            .map(|(i, _)| i)
+            .nth(index)
+            .unwrap_or(self.buf.len())
+    }
+
     pub fn clamp_cursor(&self, new_cursor_pos: usize) -> usize {
         new_cursor_pos.clamp(0, self.buf.chars().count())
     }
@@ -72,5 +256,192 @@ impl Input {
     pub fn reset_cursor(&mut self) {
         self.buf.clear();
         self.character_index = 0;
+        self.scroll.set(0);
+    }
+}
+
+/// Pasted text as one line: trailing line breaks are dropped, the others become a space each
+/// and other controls are dropped, tabs too unless `keep_tab` is set.
+pub fn paste_line(text: &str, keep_tab: bool) -> String {
+    text.trim_end_matches(['\r', '\n'])
+        .replace("\r\n", "\n")
+        .chars()
+        .map(|c| if c == '\r' || c == '\n' { ' ' } else { c })
+        .filter(|c| (keep_tab && *c == '\t') || !c.is_control())
+        .collect()
+}
+
+/// Columns a char takes in the terminal, controls count as zero.
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(text: &str, index: usize) -> Input {
+        let mut input = Input::new();
+        input.set_string(text);
+        input.character_index = index;
+        input
+    }
+
+    #[test]
+    fn home_and_end() {
+        let mut v = input("héllo", 2);
+        v.move_cursor_home();
+        assert_eq!(v.get_index(), 0);
+        v.move_cursor_end();
+        assert_eq!(v.get_index(), 5);
+    }
+
+    #[test]
+    fn delete_forward_multibyte() {
+        let mut v = input("aé中b", 1);
+        v.delete_char_forward();
+        assert_eq!(v.get_string(), "a中b");
+        assert_eq!(v.get_index(), 1);
+        v.delete_char_forward();
+        assert_eq!(v.get_string(), "ab");
+        v.move_cursor_end();
+        v.delete_char_forward();
+        assert_eq!(v.get_string(), "ab");
+    }
+
+    #[test]
+    fn word_movement() {
+        let mut v = input("AT+CMD  中文 x", 0);
+        v.move_word_right();
+        assert_eq!(v.get_index(), 6);
+        v.move_word_right();
+        assert_eq!(v.get_index(), 10);
+        v.move_word_right();
+        assert_eq!(v.get_index(), 12);
+        v.move_word_right();
+        assert_eq!(v.get_index(), 12);
+        v.move_word_left();
+        assert_eq!(v.get_index(), 11);
+        v.move_word_left();
+        assert_eq!(v.get_index(), 8);
+        v.move_word_left();
+        assert_eq!(v.get_index(), 0);
+        v.move_word_left();
+        assert_eq!(v.get_index(), 0);
+    }
+
+    #[test]
+    fn kill_word_back_and_yank() {
+        let mut v = input("send 数据 now", 7);
+        v.kill_word_back();
+        assert_eq!(v.get_string(), "send  now");
+        assert_eq!(v.get_index(), 5);
+        v.move_cursor_end();
+        v.yank();
+        assert_eq!(v.get_string(), "send  now数据");
+        assert_eq!(v.get_index(), 11);
+    }
+
+    #[test]
+    fn kill_to_start_and_end() {
+        let mut v = input("héllo wörld", 6);
+        v.kill_to_end();
+        assert_eq!(v.get_string(), "héllo ");
+        assert_eq!(v.get_index(), 6);
+        v.yank();
+        assert_eq!(v.get_string(), "héllo wörld");
+        v.character_index = 6;
+        v.kill_to_start();
+        assert_eq!(v.get_string(), "wörld");
+        assert_eq!(v.get_index(), 0);
+        v.move_cursor_end();
+        v.yank();
+        assert_eq!(v.get_string(), "wörldhéllo ");
+    }
+
+    #[test]
+    fn insert_str_in_the_middle() {
+        let mut v = input("ab", 1);
+        v.insert_str("中é");
+        assert_eq!(v.get_string(), "a中éb");
+        assert_eq!(v.get_index(), 3);
+        v.enter_char('x');
+        assert_eq!(v.get_string(), "a中éxb");
+        assert_eq!(v.byte_index(), 7);
+    }
+
+    #[test]
+    fn paste_line_breaks_become_spaces() {
+        assert_eq!(paste_line("AT\nATI", false), "AT ATI");
+        assert_eq!(paste_line("AT\r\nATI\rX", false), "AT ATI X");
+        assert_eq!(paste_line("a\n\nb", false), "a  b");
+        assert_eq!(paste_line("AT\r\n\n", false), "AT");
+        assert_eq!(paste_line("\n", false), "");
+    }
+
+    #[test]
+    fn paste_line_drops_controls() {
+        assert_eq!(paste_line("a\tb\x1b[0m中", true), "a\tb[0m中");
+        assert_eq!(paste_line("a\tb\x07", false), "ab");
+    }
+
+    #[test]
+    fn paste_at_cursor() {
+        let mut v = input("ab", 1);
+        v.paste("x\ny\n", false);
+        assert_eq!(v.get_string(), "ax yb");
+        assert_eq!(v.get_index(), 4);
+    }
+
+    #[test]
+    fn cursor_width_counts_wide_chars() {
+        let v = input("a中😀b", 3);
+        assert_eq!(v.cursor_width(), 5);
+    }
+
+    #[test]
+    fn view_fits_without_markers() {
+        let v = input("中文ab", 4);
+        let view = v.view(10);
+        assert_eq!(view.range, 0..4);
+        assert_eq!(view.cursor, 6);
+        assert!(!view.left && !view.right);
+    }
+
+    #[test]
+    fn view_follows_cursor_to_the_end() {
+        let v = input("0123456789", 10);
+        let view = v.view(6);
+        // five columns after the left marker, the last one for the cursor
+        assert_eq!(view.range, 6..10);
+        assert_eq!(view.cursor, 4);
+        assert!(view.left && !view.right);
+    }
+
+    #[test]
+    fn view_scrolls_back_and_keeps_its_offset() {
+        let mut v = input("0123456789", 10);
+        v.view(6);
+        v.move_cursor_home();
+        let view = v.view(6);
+        assert_eq!(view.range, 0..5);
+        assert_eq!(view.cursor, 0);
+        assert!(!view.left && view.right);
+        // moving right inside the window does not scroll
+        v.character_index = 3;
+        let view = v.view(6);
+        assert_eq!(view.range, 0..5);
+        assert_eq!(view.cursor, 3);
+    }
+
+    #[test]
+    fn view_pads_a_wide_char_cut_at_the_left_edge() {
+        let v = input("中中中中a", 5);
+        let view = v.view(6);
+        // offset 5 cuts the third 中 in half
+        assert_eq!(view.pad, 1);
+        assert_eq!(view.range, 3..5);
+        assert_eq!(view.cursor, 4);
+        assert!(view.left && !view.right);
     }
 }
@@ -0,0 +1,112 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::common::hex::hex_string;
+
+/// Bytes appended to every send.
+#[derive(Clone, PartialEq)]
+pub enum LineEnding {
+    None,
+    Cr,
+    Lf,
+    CrLf,
+    Custom(Vec<u8>),
+}
+
+impl LineEnding {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            LineEnding::None => b"",
+            LineEnding::Cr => b"\r",
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+            LineEnding::Custom(bytes) => bytes,
+        }
+    }
+
+    /// The next ending in the cycle, the custom one is skipped until it has been set.
+    pub fn next(&self, custom: &[u8]) -> Self {
+        match self {
+            LineEnding::None => LineEnding::Cr,
+            LineEnding::Cr => LineEnding::Lf,
+            LineEnding::Lf => LineEnding::CrLf,
+            LineEnding::CrLf if !custom.is_empty() => LineEnding::Custom(custom.to_vec()),
+            LineEnding::CrLf | LineEnding::Custom(_) => LineEnding::None,
+        }
+    }
+
+    /// Parse the name stored in a profile, `custom:` is followed by the bytes in hex.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "none" => Some(LineEnding::None),
+            "cr" => Some(LineEnding::Cr),
+            "lf" => Some(LineEnding::Lf),
+            "crlf" => Some(LineEnding::CrLf),
+            text => {
+                let hex = text.strip_prefix("custom:")?;
+                // from_str_radix would also take a sign
+                if hex.is_empty() || hex.len() % 2 != 0 || !hex.bytes().all(|v| v.is_ascii_hexdigit()) {
+                    return None;
+                }
+                let bytes: Option<Vec<u8>> = (0..hex.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                    .collect();
+                bytes.map(LineEnding::Custom)
+            }
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            LineEnding::None => String::from("none"),
+            LineEnding::Cr => String::from("cr"),
+            LineEnding::Lf => String::from("lf"),
+            LineEnding::CrLf => String::from("crlf"),
+            LineEnding::Custom(bytes) => format!("custom:{0}", hex_string(bytes, "")),
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LineEnding::None => write!(f, "None"),
+            LineEnding::Cr => write!(f, "CR"),
+            LineEnding::Lf => write!(f, "LF"),
+            LineEnding::CrLf => write!(f, "CRLF"),
+            LineEnding::Custom(bytes) => write!(f, "[{0}]", hex_string(bytes, " ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_round_trip() {
+        let endings = [
+            LineEnding::None,
+            LineEnding::Cr,
+            LineEnding::Lf,
+            LineEnding::CrLf,
+            LineEnding::Custom(vec![0x0d, 0x0a, 0x00, 0xff]),
+        ];
+        for ending in endings {
+            assert!(LineEnding::parse(&ending.name()) == Some(ending.clone()), "{ending}");
+        }
+        assert_eq!(LineEnding::Custom(vec![0xab]).name(), "custom:ab");
+        assert!(LineEnding::parse("CUSTOM:0D0A") == Some(LineEnding::Custom(vec![0x0d, 0x0a])));
+    }
+
+    #[test]
+    fn bad_names() {
+        let names = [
+            "", "crlf2", "custom:", "custom:0", "custom:0d0", "custom:zz", "custom:+a", "custom:0d 0a",
+            "custom:中0",
+        ];
+        for text in names {
+            assert!(LineEnding::parse(text).is_none(), "{text}");
+        }
+    }
+}
@@ -1,11 +1,11 @@
-use tokio_serial::SerialStream;
-
+pub mod config;
+pub mod counter;
+pub mod decoder;
+pub mod escape;
+pub mod hex;
+pub mod history;
 pub mod input;
-
-// pub fn serial_read(serial:&mut SerialStream)-> Result<Vec<u8>>{
-
-// }
-
-// pub fn serial_write()-> Result<>{
-
-// }
+pub mod line_ending;
+pub mod qa;
+pub mod receive;
+pub mod scroll;
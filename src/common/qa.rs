@@ -0,0 +1,180 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_IDLE: Duration = Duration::from_millis(100);
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Exchanges kept before the oldest are dropped.
+const MAX_EXCHANGES: usize = 10_000;
+
+/// How a response was considered complete.
+#[derive(PartialEq, Clone, Copy)]
+pub enum Outcome {
+    Pending,
+    /// The terminator was received.
+    Terminator,
+    /// Nothing more arrived within the idle gap.
+    Idle,
+    /// The timeout passed before the response completed.
+    Timeout,
+    /// The next request was sent first.
+    Interrupted,
+}
+
+pub struct Exchange {
+    request: Vec<u8>,
+    response: Vec<u8>,
+    sent: Instant,
+    /// Arrival of the last response byte.
+    last: Option<Instant>,
+    outcome: Outcome,
+}
+
+impl Exchange {
+    pub fn get_request(&self) -> &[u8] {
+        &self.request
+    }
+
+    pub fn get_response(&self) -> &[u8] {
+        &self.response
+    }
+
+    pub fn get_outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Round trip time up to the last response byte.
+    pub fn latency(&self) -> Option<Duration> {
+        self.last.map(|v| v - self.sent)
+    }
+}
+
+/// Pairs each request with the bytes received after it.
+pub struct QaSession {
+    exchanges: VecDeque<Exchange>,
+    /// Bytes that end a response, `None` relies on the idle gap and timeout alone.
+    terminator: Option<Vec<u8>>,
+    idle: Duration,
+    timeout: Duration,
+}
+
+impl QaSession {
+    pub const fn new(idle: Duration, timeout: Duration) -> Self {
+        Self {
+            exchanges: VecDeque::new(),
+            terminator: None,
+            idle,
+            timeout,
+        }
+    }
+
+    pub fn get_exchanges(&self) -> &VecDeque<Exchange> {
+        &self.exchanges
+    }
+
+    pub fn get_terminator(&self) -> Option<&[u8]> {
+        self.terminator.as_deref()
+    }
+
+    pub fn set_terminator(&mut self, terminator: Option<Vec<u8>>) {
+        self.terminator = terminator;
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn set_timing(&mut self, idle: Duration, timeout: Duration) {
+        self.idle = idle;
+        self.timeout = timeout;
+    }
+
+    fn pending(&mut self) -> Option<&mut Exchange> {
+        self.exchanges.back_mut().filter(|v| v.outcome == Outcome::Pending)
+    }
+
+    pub fn request(&mut self, data: &[u8]) {
+        if let Some(exchange) = self.pending() {
+            exchange.outcome = Outcome::Interrupted;
+        }
+        if self.exchanges.len() == MAX_EXCHANGES {
+            self.exchanges.pop_front();
+        }
+        self.exchanges.push_back(Exchange {
+            request: data.to_vec(),
+            response: vec![],
+            sent: Instant::now(),
+            last: None,
+            outcome: Outcome::Pending,
+        });
+    }
+
+    /// Add bytes received at `arrival` to the open exchange, if there is one, up to the end of the terminator.
+    pub fn receive(&mut self, data: &[u8], arrival: Instant) {
+        let terminator = self.terminator.clone();
+        let Some(exchange) = self.pending() else {
+            return;
+        };
+        exchange.last = Some(arrival);
+        let Some(terminator) = terminator.filter(|v| !v.is_empty()) else {
+            exchange.response.extend_from_slice(data);
+            return;
+        };
+        // the terminator may have started in an earlier chunk
+        let start = (exchange.response.len() + 1).saturating_sub(terminator.len());
+        exchange.response.extend_from_slice(data);
+        if let Some(i) = exchange.response[start..]
+            .windows(terminator.len())
+            .position(|v| v == terminator.as_slice())
+        {
+            // bytes after it are not part of this response
+            exchange.response.truncate(start + i + terminator.len());
+            exchange.outcome = Outcome::Terminator;
+        }
+    }
+
+    /// When the open exchange has to be checked again.
+    pub fn deadline(&self) -> Option<Instant> {
+        let exchange = self.exchanges.back().filter(|v| v.outcome == Outcome::Pending)?;
+        let timeout = exchange.sent + self.timeout;
+        Some(match exchange.last {
+            Some(last) => timeout.min(last + self.idle),
+            None => timeout,
+        })
+    }
+
+    /// Close the open exchange once its idle gap or timeout has passed.
+    pub fn tick(&mut self) {
+        let (idle, timeout) = (self.idle, self.timeout);
+        let now = Instant::now();
+        let Some(exchange) = self.pending() else {
+            return;
+        };
+        if now >= exchange.sent + timeout {
+            exchange.outcome = Outcome::Timeout;
+        } else if exchange.last.is_some_and(|v| now >= v + idle) {
+            exchange.outcome = Outcome::Idle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminator_across_chunks_ends_response() {
+        let mut qa = QaSession::new(DEFAULT_IDLE, DEFAULT_TIMEOUT);
+        qa.set_terminator(Some(b"OK\r\n".to_vec()));
+        qa.request(b"AT\r");
+        qa.receive(b"AT\r\r\nO", Instant::now());
+        qa.receive(b"K\r", Instant::now());
+        assert!(qa.get_exchanges()[0].get_outcome() == Outcome::Pending);
+        qa.receive(b"\n+URC\r\n", Instant::now());
+        let exchange = &qa.get_exchanges()[0];
+        assert!(exchange.get_outcome() == Outcome::Terminator);
+        assert_eq!(exchange.get_response(), b"AT\r\r\nOK\r\n");
+    }
+}
@@ -0,0 +1,737 @@
+use chrono::{DateTime, Local, TimeDelta};
+use strum::{Display, FromRepr};
+use std::{
+    collections::VecDeque,
+    fmt::{self, Formatter},
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use crate::common::decoder::{Decoder, Encoding, Invalid};
+
+/// Byte sequence that ends a received line.
+#[derive(Clone, PartialEq)]
+pub enum Delimiter {
+    Lf,
+    Cr,
+    CrLf,
+    Custom(u8),
+    /// No bytes, a line ends when nothing arrives for the given gap.
+    Idle(Duration),
+}
+
+impl Delimiter {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Delimiter::Lf => b"\n",
+            Delimiter::Cr => b"\r",
+            Delimiter::CrLf => b"\r\n",
+            Delimiter::Custom(byte) => std::slice::from_ref(byte),
+            Delimiter::Idle(_) => b"",
+        }
+    }
+
+    /// The next delimiter in the cycle, the custom one is skipped until it has been set.
+    pub fn next(&self, custom: Option<u8>, gap: Duration) -> Self {
+        match (self, custom) {
+            (Delimiter::Lf, _) => Delimiter::Cr,
+            (Delimiter::Cr, _) => Delimiter::CrLf,
+            (Delimiter::CrLf, Some(byte)) => Delimiter::Custom(byte),
+            (Delimiter::CrLf, None) | (Delimiter::Custom(_), _) => Delimiter::Idle(gap),
+            (Delimiter::Idle(_), _) => Delimiter::Lf,
+        }
+    }
+
+    /// Parse the name stored in a profile, an idle gap takes its length from the port settings.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "lf" => Some(Delimiter::Lf),
+            "cr" => Some(Delimiter::Cr),
+            "crlf" => Some(Delimiter::CrLf),
+            "idle" => Some(Delimiter::Idle(Duration::ZERO)),
+            text => {
+                let hex = text.strip_prefix("custom:")?;
+                if hex.len() != 2 || !hex.bytes().all(|v| v.is_ascii_hexdigit()) {
+                    return None;
+                }
+                u8::from_str_radix(hex, 16).ok().map(Delimiter::Custom)
+            }
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Delimiter::Lf => String::from("lf"),
+            Delimiter::Cr => String::from("cr"),
+            Delimiter::CrLf => String::from("crlf"),
+            Delimiter::Custom(byte) => format!("custom:{byte:02x}"),
+            Delimiter::Idle(_) => String::from("idle"),
+        }
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Delimiter::Lf => write!(f, "LF"),
+            Delimiter::Cr => write!(f, "CR"),
+            Delimiter::CrLf => write!(f, "CRLF"),
+            Delimiter::Custom(byte) => write!(f, "[{byte:02x}]"),
+            Delimiter::Idle(gap) => write!(f, "Gap {0:.2}ms", gap.as_secs_f64() * 1000.0),
+        }
+    }
+}
+
+/// How much history is kept before the oldest lines are dropped.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Capacity {
+    Lines(usize),
+    Megabytes(usize),
+}
+
+impl Capacity {
+    /// Parse `10000`, `10000 lines` or `64MB`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        let (number, unit) = text.split_at(text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len()));
+        let number: usize = number.parse().ok().filter(|v| *v > 0)?;
+        match unit.trim() {
+            "" | "l" | "lines" => Some(Capacity::Lines(number)),
+            "m" | "mb" => Some(Capacity::Megabytes(number)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Capacity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Capacity::Lines(lines) => write!(f, "{lines} lines"),
+            Capacity::Megabytes(size) => write!(f, "{size}MB"),
+        }
+    }
+}
+
+pub const DEFAULT_CAPACITY: Capacity = Capacity::Lines(100_000);
+
+/// Longest line kept in progress, data without delimiters is cut into lines of this size.
+const MAX_LINE: usize = 64 * 1024;
+
+/// Which time is shown in front of each received line.
+#[derive(PartialEq, Clone, Copy, Display, FromRepr)]
+pub enum Timestamp {
+    Off,
+    /// Local wall clock time.
+    Wall,
+    /// Time since the previous line.
+    Delta,
+    /// Time since the port was connected.
+    Since,
+}
+
+impl Timestamp {
+    pub fn next(self) -> Self {
+        let next_index = (self as usize).saturating_add(1);
+        Self::from_repr(next_index).unwrap_or(Timestamp::Off)
+    }
+
+    /// Parse the name stored in a profile.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_lowercase().as_str() {
+            "off" => Some(Timestamp::Off),
+            "wall" => Some(Timestamp::Wall),
+            "delta" => Some(Timestamp::Delta),
+            "since" => Some(Timestamp::Since),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Timestamp::Off => "off",
+            Timestamp::Wall => "wall",
+            Timestamp::Delta => "delta",
+            Timestamp::Since => "since",
+        }
+    }
+}
+
+/// Arrival time of a received chunk, kept so lines can be split again without losing it.
+#[derive(Clone, Copy)]
+pub struct Stamp {
+    time: DateTime<Local>,
+    /// Time since the port was connected, filled in by the buffer.
+    since: TimeDelta,
+    /// Monotonic arrival time, used to measure idle gaps.
+    instant: Instant,
+}
+
+impl Stamp {
+    /// Take the time right after a read returns, before the data waits in any queue.
+    pub fn now() -> Self {
+        Self {
+            time: Local::now(),
+            since: TimeDelta::zero(),
+            instant: Instant::now(),
+        }
+    }
+
+    pub fn get_instant(&self) -> Instant {
+        self.instant
+    }
+
+    fn since(mut self, connected: Option<DateTime<Local>>) -> Self {
+        self.since = self.time - connected.unwrap_or(self.time);
+        self
+    }
+}
+
+/// Whether a line was received or sent.
+#[derive(PartialEq, Clone, Copy)]
+pub enum Direction {
+    Rx,
+    /// A sent frame, with the number of bytes received before it was sent.
+    Tx(usize),
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Rx => write!(f, "RX<"),
+            Direction::Tx(_) => write!(f, "TX>"),
+        }
+    }
+}
+
+pub struct RxLine {
+    direction: Direction,
+    /// Raw bytes including the delimiter.
+    bytes: Vec<u8>,
+    /// Offset of the first received byte at or after the line, lines are ordered by it.
+    offset: usize,
+    /// Decoded text without the delimiter.
+    text: String,
+    /// Arrival of the first byte of the line.
+    stamp: Stamp,
+    delta: TimeDelta,
+}
+
+impl RxLine {
+    fn new(stamp: Stamp, previous: Option<&RxLine>) -> Self {
+        Self {
+            direction: Direction::Rx,
+            bytes: vec![],
+            offset: 0,
+            text: String::new(),
+            stamp,
+            delta: previous.map_or(TimeDelta::zero(), |v| stamp.time - v.stamp.time),
+        }
+    }
+
+    pub fn get_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn get_direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// The line's time in the given style, followed by a space, empty when timestamps are off.
+    pub fn timestamp(&self, timestamp: Timestamp) -> String {
+        let seconds = |v: TimeDelta| {
+            let ms = v.num_milliseconds();
+            format!("{0}.{1:03}", ms / 1000, ms % 1000)
+        };
+        match timestamp {
+            Timestamp::Off => String::new(),
+            Timestamp::Wall => format!("{0} ", self.stamp.time.format("%H:%M:%S%.3f")),
+            Timestamp::Delta => format!("+{0} ", seconds(self.delta)),
+            Timestamp::Since => format!("{0} ", seconds(self.stamp.since)),
+        }
+    }
+}
+
+/// Received data split into lines, the last line is in progress until its delimiter arrives.
+/// The oldest lines are dropped once the history outgrows its capacity.
+pub struct ReceiveBuffer {
+    lines: VecDeque<RxLine>,
+    partial: RxLine,
+    /// Arrival of each chunk still held, with the offset of its first byte.
+    chunks: VecDeque<(usize, Stamp)>,
+    connected: Option<DateTime<Local>>,
+    delimiter: Delimiter,
+    /// Time one character takes on the wire at the port's settings.
+    char_time: Duration,
+    encoding: Encoding,
+    invalid: Invalid,
+    capacity: Capacity,
+    /// Received bytes in `lines` and `partial`.
+    size: usize,
+    /// Sent bytes in `lines`.
+    tx_size: usize,
+    dropped_lines: usize,
+    dropped_bytes: usize,
+}
+
+impl ReceiveBuffer {
+    pub fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            partial: RxLine::new(Stamp::now(), None),
+            chunks: VecDeque::new(),
+            connected: None,
+            delimiter: Delimiter::Lf,
+            char_time: Duration::ZERO,
+            encoding: Encoding::Utf8,
+            invalid: Invalid::Replace,
+            capacity: DEFAULT_CAPACITY,
+            size: 0,
+            tx_size: 0,
+            dropped_lines: 0,
+            dropped_bytes: 0,
+        }
+    }
+
+    /// Mark the port as connected now, later lines count their time since then.
+    pub fn connected(&mut self) {
+        self.connected = Some(Local::now());
+    }
+
+    /// Add a chunk that was read at `stamp`.
+    pub fn push(&mut self, data: &[u8], stamp: Stamp) {
+        self.split(data, stamp.since(self.connected));
+        self.evict();
+    }
+
+    /// Add a sent frame as a line of its own, ahead of the line still being received.
+    pub fn transmit(&mut self, data: &[u8]) {
+        let mut line = RxLine::new(Stamp::now().since(self.connected), None);
+        line.direction = Direction::Tx(self.dropped_bytes + self.size);
+        line.bytes = data.to_vec();
+        self.insert(line);
+        self.evict();
+    }
+
+    fn insert(&mut self, mut line: RxLine) {
+        line.delta = RxLine::new(line.stamp, self.lines.back()).delta;
+        line.offset = self.dropped_bytes + self.size - self.partial.bytes.len();
+        line.text = self.decode(&line.bytes, true);
+        self.tx_size += line.bytes.len();
+        self.lines.push_back(line);
+    }
+
+    fn split(&mut self, data: &[u8], stamp: Stamp) {
+        if data.is_empty() {
+            return;
+        }
+        if let Delimiter::Idle(gap) = self.delimiter {
+            // a chunk arrives once its last byte is in, the time its bytes took on the wire was not silence
+            let transfer = self.char_time.saturating_mul(data.len().try_into().unwrap_or(u32::MAX));
+            let idle = self.chunks.back().is_some_and(|(_, v)| {
+                stamp.instant.saturating_duration_since(v.instant).saturating_sub(transfer) > gap
+            });
+            if idle && !self.partial.bytes.is_empty() {
+                let mut line = std::mem::replace(&mut self.partial, RxLine::new(stamp, None));
+                line.text = self.decode(&line.bytes, true);
+                self.lines.push_back(line);
+            }
+        }
+        self.chunks.push_back((self.dropped_bytes + self.size, stamp));
+        if self.partial.bytes.is_empty() {
+            self.partial = RxLine::new(stamp, self.lines.back());
+        }
+        let delimiter = self.delimiter.as_bytes().to_vec();
+        let mut bytes = std::mem::take(&mut self.partial.bytes);
+        // a delimiter may have started at the end of the previous chunk
+        let mut search = (bytes.len() + 1).saturating_sub(delimiter.len());
+        bytes.extend_from_slice(data);
+        self.size += data.len();
+        let base = self.dropped_bytes + self.size - bytes.len();
+        let mut begin = 0;
+        loop {
+            let found = match delimiter.len() {
+                0 => None,
+                len => bytes[search..].windows(len).position(|v| v == delimiter.as_slice()),
+            };
+            let end = match found {
+                Some(i) => search + i + delimiter.len(),
+                None if bytes.len() - begin > MAX_LINE => begin + MAX_LINE,
+                None => break,
+            };
+            let end = end.min(begin + MAX_LINE);
+            let mut line = std::mem::replace(&mut self.partial, RxLine::new(stamp, None));
+            line.bytes = bytes[begin..end].to_vec();
+            line.offset = base + begin;
+            line.text = self.decode(&line.bytes, true);
+            self.partial.delta = stamp.time - line.stamp.time;
+            self.lines.push_back(line);
+            begin = end;
+            search = end;
+        }
+        bytes.drain(..begin);
+        self.partial.offset = base + begin;
+        self.partial.text = self.decode(&bytes, false);
+        self.partial.bytes = bytes;
+    }
+
+    /// Drop the oldest lines until the history fits its capacity again.
+    fn evict(&mut self) {
+        let full = |v: &Self| match v.capacity {
+            Capacity::Lines(lines) => v.line_count() > lines,
+            Capacity::Megabytes(size) => v.size + v.tx_size > size * 1024 * 1024,
+        };
+        while full(self) {
+            let Some(line) = self.lines.pop_front() else {
+                break;
+            };
+            match line.direction {
+                Direction::Rx => {
+                    self.size -= line.bytes.len();
+                    self.dropped_bytes += line.bytes.len();
+                }
+                Direction::Tx(_) => self.tx_size -= line.bytes.len(),
+            }
+            self.dropped_lines += 1;
+        }
+        // keep the chunk the first held byte arrived in
+        while self.chunks.get(1).is_some_and(|v| v.0 <= self.dropped_bytes) {
+            self.chunks.pop_front();
+        }
+    }
+
+    /// Decode a line without its delimiter, an unfinished one keeps an incomplete trailing sequence hidden.
+    fn decode(&self, bytes: &[u8], complete: bool) -> String {
+        let bytes = bytes.strip_suffix(self.delimiter.as_bytes()).unwrap_or(bytes);
+        let mut decoder = Decoder::new(self.encoding, self.invalid);
+        let mut text = decoder.decode(bytes);
+        if complete {
+            text.push_str(&decoder.flush());
+        }
+        // control characters would move the cursor around the terminal
+        text.retain(|c| c == '\t' || !c.is_control());
+        text
+    }
+
+    /// Complete lines followed by the in-progress one when it has any data.
+    pub fn lines(&self) -> impl Iterator<Item = &RxLine> {
+        let partial = (!self.partial.bytes.is_empty()).then_some(&self.partial);
+        self.lines.iter().chain(partial)
+    }
+
+    /// The lines in `range`, counted like `lines`.
+    pub fn window(&self, range: Range<usize>) -> impl Iterator<Item = &RxLine> {
+        let complete = range.start.min(self.lines.len())..range.end.min(self.lines.len());
+        let partial = (range.contains(&self.lines.len()) && !self.partial.bytes.is_empty())
+            .then_some(&self.partial);
+        self.lines.range(complete).chain(partial)
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.bytes.is_empty())
+    }
+
+    /// Number of received bytes held.
+    pub fn byte_count(&self) -> usize {
+        self.size
+    }
+
+    /// The held lines as text, one per line with their timestamps, for saving to a file.
+    pub fn export(&self, timestamp: Timestamp) -> String {
+        let mut text = String::new();
+        let echo = self.tx_size > 0;
+        for line in self.lines() {
+            text.push_str(&line.timestamp(timestamp));
+            if echo {
+                text.push_str(&format!("{0} ", line.direction));
+            }
+            text.push_str(line.get_text());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Received lines, without the sent ones.
+    fn received(&self) -> impl Iterator<Item = &RxLine> {
+        self.lines().filter(|v| v.direction == Direction::Rx)
+    }
+
+    /// Everything received that is still held, in order.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.received().flat_map(|v| v.bytes.iter().copied()).collect()
+    }
+
+    /// The received bytes in `range`, offsets start at the oldest byte still held.
+    pub fn byte_window(&self, range: Range<usize>) -> Vec<u8> {
+        let (start, end) = (self.dropped_bytes + range.start, self.dropped_bytes + range.end);
+        // jump to the last line starting at or before the window instead of walking the history
+        let first = self.lines.partition_point(|v| v.offset <= start).saturating_sub(1);
+        let partial = (!self.partial.bytes.is_empty()).then_some(&self.partial);
+        let mut bytes = vec![];
+        for line in self.lines.range(first..).chain(partial) {
+            if line.direction != Direction::Rx || line.offset + line.bytes.len() <= start {
+                continue;
+            }
+            if line.offset >= end {
+                break;
+            }
+            let from = start.saturating_sub(line.offset);
+            let to = (end - line.offset).min(line.bytes.len());
+            bytes.extend_from_slice(&line.bytes[from..to]);
+        }
+        bytes
+    }
+
+    /// Lines and bytes that have been dropped to stay within the capacity.
+    pub fn get_dropped(&self) -> (usize, usize) {
+        (self.dropped_lines, self.dropped_bytes)
+    }
+
+    pub fn get_capacity(&self) -> Capacity {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: Capacity) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    /// Set the time one character takes on the wire, idle gaps are measured past it.
+    pub fn set_char_time(&mut self, char_time: Duration) {
+        self.char_time = char_time;
+    }
+
+    pub fn get_delimiter(&self) -> &Delimiter {
+        &self.delimiter
+    }
+
+    /// Split the held history again on `delimiter`, keeping the arrival times.
+    pub fn set_delimiter(&mut self, delimiter: Delimiter) {
+        let bytes = self.bytes();
+        let chunks = std::mem::take(&mut self.chunks);
+        let mut sent = std::mem::take(&mut self.lines)
+            .into_iter()
+            .filter(|v| v.direction != Direction::Rx)
+            .peekable();
+        self.delimiter = delimiter;
+        self.partial = RxLine::new(self.partial.stamp, None);
+        let start = self.dropped_bytes;
+        self.size = 0;
+        self.tx_size = 0;
+        for (i, (offset, stamp)) in chunks.iter().enumerate() {
+            // frames sent before this chunk arrived go in first
+            while let Some(line) =
+                sent.next_if(|v| matches!(v.direction, Direction::Tx(sent_at) if sent_at <= *offset))
+            {
+                self.insert(line);
+            }
+            let end = chunks.get(i + 1).map_or(bytes.len(), |v| v.0 - start);
+            self.split(&bytes[offset.saturating_sub(start)..end], *stamp);
+        }
+        for line in sent {
+            self.insert(line);
+        }
+        self.evict();
+    }
+
+    /// Decode the held history again with the new settings.
+    pub fn set_decoding(&mut self, encoding: Encoding, invalid: Invalid) {
+        self.encoding = encoding;
+        self.invalid = invalid;
+        for i in 0..self.lines.len() {
+            self.lines[i].text = self.decode(&self.lines[i].bytes, true);
+        }
+        self.partial.text = self.decode(&self.partial.bytes, false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 10 bits per character at 115200 baud.
+    const CHAR_TIME: Duration = Duration::from_nanos(86_806);
+
+    fn idle_buffer() -> ReceiveBuffer {
+        let mut buf = ReceiveBuffer::new();
+        buf.set_char_time(CHAR_TIME);
+        buf.set_delimiter(Delimiter::Idle(CHAR_TIME.mul_f64(3.5)));
+        buf
+    }
+
+    fn stamp(start: Instant, micros: u64) -> Stamp {
+        Stamp {
+            time: Local::now(),
+            since: TimeDelta::zero(),
+            instant: start + Duration::from_micros(micros),
+        }
+    }
+
+    fn texts(buf: &ReceiveBuffer) -> Vec<&str> {
+        buf.lines().map(RxLine::get_text).collect()
+    }
+
+    fn offsets(buf: &ReceiveBuffer) -> Vec<usize> {
+        buf.chunks.iter().map(|v| v.0).collect()
+    }
+
+    #[test]
+    fn delimiter_name_round_trip() {
+        let delimiters = [Delimiter::Lf, Delimiter::Cr, Delimiter::CrLf, Delimiter::Custom(0x7e)];
+        for delimiter in delimiters {
+            assert!(Delimiter::parse(&delimiter.name()) == Some(delimiter.clone()), "{delimiter}");
+        }
+        assert!(Delimiter::parse("idle") == Some(Delimiter::Idle(Duration::ZERO)));
+        for text in ["", "custom:", "custom:7", "custom:7e7e", "custom:+7", "gap"] {
+            assert!(Delimiter::parse(text).is_none(), "{text}");
+        }
+    }
+
+    #[test]
+    fn crlf_across_chunks() {
+        let data = b"ab\r\ncd\r\nef";
+        for i in 0..=data.len() {
+            let mut buf = ReceiveBuffer::new();
+            buf.set_delimiter(Delimiter::CrLf);
+            buf.push(&data[..i], Stamp::now());
+            buf.push(&data[i..], Stamp::now());
+            assert_eq!(texts(&buf), ["ab", "cd", "ef"], "split at {i}");
+            assert_eq!(buf.bytes(), data, "split at {i}");
+        }
+    }
+
+    #[test]
+    fn multibyte_across_chunks() {
+        let data = "中文é\nx😀".as_bytes();
+        for i in 0..=data.len() {
+            let mut buf = ReceiveBuffer::new();
+            buf.push(&data[..i], Stamp::now());
+            buf.push(&data[i..], Stamp::now());
+            assert_eq!(texts(&buf), ["中文é", "x😀"], "split at {i}");
+        }
+    }
+
+    #[test]
+    fn partial_hides_incomplete_sequence() {
+        let mut buf = ReceiveBuffer::new();
+        buf.push("a中".as_bytes().split_last().unwrap().1, Stamp::now());
+        assert_eq!(texts(&buf), ["a"]);
+    }
+
+    #[test]
+    fn long_line_is_cut() {
+        let mut buf = ReceiveBuffer::new();
+        buf.push(&vec![b'a'; MAX_LINE - 1], Stamp::now());
+        buf.push(b"bcd\n", Stamp::now());
+        let lengths: Vec<_> = buf.lines().map(|v| v.get_bytes().len()).collect();
+        assert_eq!(lengths, [MAX_LINE, 3]);
+        assert_eq!(buf.lines().nth(1).unwrap().get_bytes(), b"cd\n");
+    }
+
+    #[test]
+    fn split_records_chunk_offsets() {
+        let mut buf = ReceiveBuffer::new();
+        buf.push(b"ab", Stamp::now());
+        buf.push(b"", Stamp::now());
+        buf.push(b"c\nd", Stamp::now());
+        assert_eq!(offsets(&buf), [0, 2]);
+        assert_eq!(texts(&buf), ["abc", "d"]);
+    }
+
+    #[test]
+    fn set_delimiter_resplits() {
+        let mut buf = ReceiveBuffer::new();
+        buf.push(b"a\rb", Stamp::now());
+        buf.push(b"\nc\r", Stamp::now());
+        assert_eq!(texts(&buf), ["ab", "c"]);
+        buf.set_delimiter(Delimiter::Cr);
+        let lines: Vec<_> = buf.lines().map(RxLine::get_bytes).collect();
+        assert_eq!(lines, [&b"a\r"[..], b"b\nc\r"]);
+        assert_eq!(offsets(&buf), [0, 3]);
+    }
+
+    #[test]
+    fn evict_keeps_first_held_chunk() {
+        let mut buf = ReceiveBuffer::new();
+        buf.set_capacity(Capacity::Lines(2));
+        buf.push(b"a\n", Stamp::now());
+        buf.push(b"bb\nc", Stamp::now());
+        buf.push(b"c\nd", Stamp::now());
+        // "a\n" and "bb\n" are gone, the chunk holding the start of "cc\n" stays
+        assert_eq!(buf.get_dropped(), (2, 5));
+        assert_eq!(offsets(&buf), [2, 6]);
+        // the dropped bytes are not split again, the first chunk now starts at the first held byte
+        buf.set_delimiter(Delimiter::Cr);
+        assert_eq!(buf.get_dropped(), (2, 5));
+        assert_eq!(offsets(&buf), [5, 6]);
+        assert_eq!(buf.bytes(), b"cc\nd");
+        assert_eq!(buf.line_count(), 1);
+    }
+
+    #[test]
+    fn byte_window_matches_bytes() {
+        let mut buf = ReceiveBuffer::new();
+        buf.set_capacity(Capacity::Lines(6));
+        for (i, chunk) in [&b"ab\ncd"[..], b"e", b"\n\nfgh", b"i\nj", b"k\nlm"].iter().enumerate() {
+            buf.push(chunk, Stamp::now());
+            buf.transmit(&[b'0' + i as u8; 2]);
+        }
+        let check = |buf: &ReceiveBuffer| {
+            let bytes = buf.bytes();
+            for start in 0..=bytes.len() {
+                for end in start..=bytes.len() + 2 {
+                    let expected = &bytes[start..end.min(bytes.len())];
+                    assert_eq!(buf.byte_window(start..end), expected, "{start}..{end}");
+                }
+            }
+        };
+        assert!(buf.get_dropped().1 > 0);
+        check(&buf);
+        buf.set_delimiter(Delimiter::Custom(b'g'));
+        check(&buf);
+    }
+
+    #[test]
+    fn sent_lines_survive_resplit() {
+        let mut buf = ReceiveBuffer::new();
+        buf.push(b"ab", Stamp::now());
+        buf.transmit(b"AT");
+        buf.push(b"c\nde", Stamp::now());
+        buf.transmit(b"ATI");
+        buf.push(b"\n", Stamp::now());
+        let order = |buf: &ReceiveBuffer| -> Vec<(String, String)> {
+            buf.lines().map(|v| (v.get_direction().to_string(), v.get_text().to_string())).collect()
+        };
+        let expected = [("TX>", "AT"), ("RX<", "abc"), ("TX>", "ATI"), ("RX<", "de")];
+        let expected: Vec<_> = expected.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+        assert_eq!(order(&buf), expected);
+        buf.set_delimiter(Delimiter::Cr);
+        buf.set_delimiter(Delimiter::Lf);
+        assert_eq!(order(&buf), expected);
+    }
+
+    #[test]
+    fn idle_continuous_stream() {
+        let mut buf = idle_buffer();
+        let start = Instant::now();
+        for i in 0..5 {
+            buf.push(&[i; 8], stamp(start, 700 * i as u64));
+        }
+        assert_eq!(buf.line_count(), 1);
+        assert_eq!(buf.lines().next().unwrap().get_bytes().len(), 40);
+    }
+
+    #[test]
+    fn idle_gap_ends_frame() {
+        let mut buf = idle_buffer();
+        let start = Instant::now();
+        buf.push(&[1; 8], stamp(start, 0));
+        buf.push(&[2; 8], stamp(start, 700));
+        buf.push(&[3; 8], stamp(start, 700 + 2000));
+        let frames: Vec<_> = buf.lines().map(|v| v.get_bytes().to_vec()).collect();
+        assert_eq!(frames, [[[1; 8], [2; 8]].concat(), vec![3; 8]]);
+    }
+}
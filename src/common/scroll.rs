@@ -0,0 +1,75 @@
+use std::{cell::Cell, ops::Range};
+
+/// Scroll position of a list that grows at the bottom.
+pub struct Scroll {
+    /// Rows between the bottom of the view and the last row, 0 follows new data.
+    offset: usize,
+    /// Rows that arrived while the view was scrolled up.
+    unseen: usize,
+    /// Height of the view as last drawn, used as the page size.
+    height: Cell<usize>,
+}
+
+impl Scroll {
+    pub const fn new() -> Self {
+        Self {
+            offset: 0,
+            unseen: 0,
+            height: Cell::new(0),
+        }
+    }
+
+    pub fn is_following(&self) -> bool {
+        self.offset == 0
+    }
+
+    pub fn get_unseen(&self) -> usize {
+        self.unseen
+    }
+
+    /// Keep the view still while `rows` are appended, unless it follows the tail.
+    pub fn grow(&mut self, rows: usize) {
+        if !self.is_following() {
+            self.offset += rows;
+            self.unseen += rows;
+        }
+    }
+
+    pub fn up(&mut self, rows: usize, total: usize) {
+        let max = total.saturating_sub(self.height.get());
+        self.offset = self.offset.saturating_add(rows).min(max);
+    }
+
+    pub fn down(&mut self, rows: usize) {
+        self.offset = self.offset.saturating_sub(rows);
+        self.unseen = self.unseen.min(self.offset);
+    }
+
+    pub fn page_up(&mut self, total: usize) {
+        self.up(self.page(), total);
+    }
+
+    pub fn page_down(&mut self) {
+        self.down(self.page());
+    }
+
+    pub fn top(&mut self, total: usize) {
+        self.up(total, total);
+    }
+
+    pub fn bottom(&mut self) {
+        self.offset = 0;
+        self.unseen = 0;
+    }
+
+    /// Rows of a list of `total` rows that fit in a view `height` rows high.
+    pub fn window(&self, total: usize, height: usize) -> Range<usize> {
+        self.height.set(height);
+        let end = total.saturating_sub(self.offset).max(height.min(total));
+        end.saturating_sub(height)..end
+    }
+
+    fn page(&self) -> usize {
+        self.height.get().saturating_sub(1).max(1)
+    }
+}
@@ -2,14 +2,19 @@ use std::{error::Error, io};
 
 use ratatui::{
     crossterm::{
-        event::{DisableMouseCapture, EnableMouseCapture},
+        event::{
+            DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        },
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
     prelude::*
 };
+use clap::Parser;
+use cli::Cli;
 use ui::AppContext;
 
+mod cli;
 mod ui;
 mod common;
 
@@ -17,22 +22,33 @@ mod common;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    if cli.list {
+        cli::list_ports()?;
+        return Ok(());
+    }
+
+    let mut context = AppContext::new();
+    context.load_config();
+    context.apply_cli(&cli)?;
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
-    let res = AppContext::new().run_app(&mut terminal).await;
+    let res = context.run_app(&mut terminal).await;
 
     // restore terminal
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -1,23 +1,31 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
 use indoc::indoc;
 use ratatui::{
     backend::Backend,
     crossterm::{
-        event::{self, Event, KeyCode, KeyEventKind},
+        event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
         style::Color,
     },
-    layout::{Constraint, Layout},
+    layout::{Constraint, Layout, Rect},
     style::Stylize,
     text::Line,
-    widgets::Paragraph,
+    widgets::{Block, Paragraph},
     Frame, Terminal,
 };
 use strum::{Display, EnumIter, FromRepr, IntoEnumIterator};
-use tokio_serial::{DataBits, FlowControl, Parity, SerialPortInfo, StopBits};
+use tokio_serial::{DataBits, FlowControl, Parity, SerialPortInfo, SerialPortType, StopBits};
 
+use crate::common::input::Input;
 use crate::ui::{AppContext, Page};
 
 #[derive(PartialEq, Clone, Copy, Display, FromRepr, EnumIter)]
 enum Menu {
+    #[strum(to_string = "Profile")]
+    Profile,
     #[strum(to_string = "Serial Port")]
     SerialPort,
     #[strum(to_string = "Baud Rate")]
@@ -45,10 +53,95 @@ impl Menu {
     fn next(self) -> Self {
         let current_index = self as usize;
         let next_index = current_index.saturating_add(1);
-        Self::from_repr(next_index).unwrap_or(Menu::SerialPort)
+        Self::from_repr(next_index).unwrap_or(Menu::Profile)
+    }
+}
+
+/// Field of `SerialPortInfo` the port list is sorted by.
+#[derive(PartialEq, Clone, Copy, Display, FromRepr, EnumIter)]
+enum SortBy {
+    #[strum(to_string = "Name")]
+    Name,
+    #[strum(to_string = "VID")]
+    Vid,
+    #[strum(to_string = "PID")]
+    Pid,
+    #[strum(to_string = "Manufacturer")]
+    Manufacturer,
+    #[strum(to_string = "Product")]
+    Product,
+    #[strum(to_string = "Serial Number")]
+    SerialNumber,
+}
+
+impl SortBy {
+    fn next(self) -> Self {
+        let next_index = (self as usize).saturating_add(1);
+        Self::from_repr(next_index).unwrap_or(SortBy::Name)
+    }
+}
+
+/// What the text input on the page is editing.
+#[derive(PartialEq, Clone, Copy)]
+enum Edit {
+    Profile,
+    Path,
+    BaudRate,
+    Filter,
+}
+
+/// Text of `field` for `info`, empty when the port does not have it.
+fn port_field(info: &SerialPortInfo, field: SortBy) -> String {
+    let SerialPortType::UsbPort(usb) = &info.port_type else {
+        return match field {
+            SortBy::Name => info.port_name.clone(),
+            _ => String::new(),
+        };
+    };
+    match field {
+        SortBy::Name => info.port_name.clone(),
+        SortBy::Vid => format!("{:04x}", usb.vid),
+        SortBy::Pid => format!("{:04x}", usb.pid),
+        SortBy::Manufacturer => usb.manufacturer.clone().unwrap_or_default(),
+        SortBy::Product => usb.product.clone().unwrap_or_default(),
+        SortBy::SerialNumber => usb.serial_number.clone().unwrap_or_default(),
     }
 }
 
+fn port_label(info: &SerialPortInfo) -> String {
+    let SerialPortType::UsbPort(usb) = &info.port_type else {
+        return info.port_name.clone();
+    };
+    let name = usb.product.as_ref().or(usb.manufacturer.as_ref());
+    format!(
+        "{0} [{1:04x}:{2:04x}] {3}",
+        info.port_name,
+        usb.vid,
+        usb.pid,
+        name.map(|v| v.as_str()).unwrap_or_default()
+    )
+}
+
+fn port_detail(info: &SerialPortInfo) -> Vec<Line<'static>> {
+    let mut line_list = vec![Line::from(format!("Path: {0}", info.port_name))];
+    match &info.port_type {
+        SerialPortType::UsbPort(_) => {
+            line_list.push(Line::from("Type: USB"));
+            for field in SortBy::iter().skip(1) {
+                let value = port_field(info, field);
+                line_list.push(Line::from(format!(
+                    "{field}: {0}",
+                    if value.is_empty() { "-" } else { &value }
+                )));
+            }
+        }
+        SerialPortType::PciPort => line_list.push(Line::from("Type: PCI")),
+        SerialPortType::BluetoothPort => line_list.push(Line::from("Type: Bluetooth")),
+        SerialPortType::Unknown => line_list.push(Line::from("Type: Unknown")),
+    }
+    line_list
+}
+
 fn logo() -> String {
     let str = indoc! {r"
  ______     ______     ______     __     ______     __         ______   ______     ______     __        
@@ -57,7 +150,75 @@ fn logo() -> String {
  \/\_____\  \ \_____\  \ \_\ \_\  \ \_\  \ \_\ \_\  \ \_____\    \ \_\  \ \_____\  \ \_____\  \ \_____\ 
   \/_____/   \/_____/   \/_/ /_/   \/_/   \/_/\/_/   \/_____/     \/_/   \/_____/   \/_____/   \/_____/ 
     "};
-    return String::from(str);
+    String::from(str)
+}
+
+/// How often the port list is refreshed while the page is open.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Number of completion candidates shown below the path.
+const MAX_CANDIDATES: usize = 10;
+
+/// Complete `path` against the entries of its directory, relative to `/dev` when not absolute.
+/// Returns the completed path and, when ambiguous, the matching names.
+fn complete_path(path: &str) -> (String, Vec<String>) {
+    let full = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/dev/{path}")
+    };
+    let (dir, prefix) = full.split_at(full.rfind('/').unwrap_or(0) + 1);
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (path.to_string(), vec![]);
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|v| v.ok())
+        .map(|v| v.file_name().to_string_lossy().into_owned())
+        .filter(|v| v.starts_with(prefix))
+        .collect();
+    names.sort();
+
+    let Some(first) = names.first() else {
+        return (full, vec![]);
+    };
+    let mut common = first.clone();
+    for name in names.iter() {
+        while !name.starts_with(&common) {
+            common.pop();
+        }
+    }
+    let mut completed = format!("{dir}{common}");
+    if names.len() == 1 {
+        if Path::new(&completed).is_dir() {
+            completed.push('/');
+        }
+        names.clear();
+    }
+    (completed, names)
+}
+
+/// Check that `path` names a device that can be opened as a serial port.
+fn check_path(path: &str) -> Result<(), String> {
+    if path.is_empty() {
+        return Err(String::from("no serial port selected"));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+
+        let metadata = std::fs::metadata(path).map_err(|e| format!("{path}: {e}"))?;
+        if !metadata.file_type().is_char_device() {
+            return Err(format!("{path}: not a character device"));
+        }
+    }
+    Ok(())
+}
+
+fn check_baud_rate(text: &str) -> Result<u32, String> {
+    match text.trim().parse::<u32>() {
+        Ok(0) | Err(_) => Err(format!("{text}: not a valid baud rate")),
+        Ok(baud_rate) => Ok(baud_rate),
+    }
 }
 
 const BAUD_RATE: [u32; 20] = [
@@ -87,19 +248,64 @@ pub struct IndexPage {
     select: bool,
     index: usize,
     port_list: Vec<SerialPortInfo>,
+    scan_time: Instant,
+    editing: Option<Edit>,
+    input: Input,
+    candidates: Vec<String>,
+    message: Option<String>,
+    filter: String,
+    sort_by: SortBy,
 }
 
 impl IndexPage {
-    pub const fn new(info: Vec<SerialPortInfo>) -> Self {
-        return Self {
+    pub fn new(info: Vec<SerialPortInfo>) -> Self {
+        Self {
             position: Menu::SerialPort,
             select: false,
             index: 0,
             port_list: info,
-        };
+            scan_time: Instant::now(),
+            editing: None,
+            input: Input::new(),
+            candidates: vec![],
+            message: None,
+            filter: String::new(),
+            sort_by: SortBy::Name,
+        }
+    }
+
+    /// Ports matching the filter, in the selected order.
+    fn visible_ports(&self) -> Vec<&SerialPortInfo> {
+        let filter = self.filter.to_lowercase();
+        let mut list: Vec<&SerialPortInfo> = self
+            .port_list
+            .iter()
+            .filter(|v| {
+                SortBy::iter().any(|field| port_field(v, field).to_lowercase().contains(&filter))
+            })
+            .collect();
+        list.sort_by_key(|v| port_field(v, self.sort_by));
+        list
+    }
+
+    /// The menu item being edited in place, if any.
+    fn edit_menu(&self) -> Option<Menu> {
+        match self.editing {
+            Some(Edit::Profile) => Some(Menu::Profile),
+            Some(Edit::Path) => Some(Menu::SerialPort),
+            Some(Edit::BaudRate) => Some(Menu::BaudRate),
+            _ => None,
+        }
+    }
+
+    fn rescan(&mut self) {
+        if let Ok(list) = tokio_serial::available_ports() {
+            self.port_list = list;
+        }
+        self.scan_time = Instant::now();
     }
 
-    fn title(&self, position: Menu, value: &String) -> String {
+    fn title(&self, position: Menu, value: &str) -> String {
         let mut text = if self.position == position && self.select {
             String::from("<")
         } else if self.position == position {
@@ -109,16 +315,16 @@ impl IndexPage {
         };
         text.push_str(&position.to_string());
         text.push_str(": ");
-        text.push_str(value.as_str());
-        return text;
+        text.push_str(value);
+        text
     }
 
-    fn add_item(&self, text: &mut Vec<Line>, key: usize, value: &String) {
+    fn add_item(&self, text: &mut Vec<Line>, key: usize, value: &str) {
         let mut str = String::new();
         str.push_str("   ");
         str.push_str(key.to_string().as_str());
         str.push_str(": ");
-        str.push_str(value.as_str());
+        str.push_str(value);
 
         text.push(if self.index == key {
             Line::from(str).fg(Color::Yellow)
@@ -139,6 +345,68 @@ impl IndexPage {
         self.index /= 10
     }
 
+    fn edit(&mut self, edit: Edit, key: &KeyEvent, context: &mut AppContext) {
+        if key.code != KeyCode::Tab {
+            self.candidates.clear();
+        }
+        match key.code {
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.enter_char(c)
+            }
+            KeyCode::Backspace => self.input.delete_char(),
+            KeyCode::Left => self.input.move_cursor_left(),
+            KeyCode::Right => self.input.move_cursor_right(),
+            KeyCode::Tab if edit == Edit::Path => {
+                let (path, candidates) = complete_path(self.input.get_string());
+                self.input.set_string(&path);
+                self.candidates = candidates;
+            }
+            KeyCode::Enter => {
+                let text = self.input.get_string();
+                let result = match edit {
+                    Edit::Profile if text.trim().is_empty() => {
+                        Err(String::from("profile name is empty"))
+                    }
+                    Edit::Profile => context.save_profile(text.trim()),
+                    Edit::Path => check_path(text).map(|_| context.path = text.clone()),
+                    Edit::BaudRate => check_baud_rate(text).map(|v| context.baud_rate = v),
+                    Edit::Filter => Ok(()),
+                };
+                match result {
+                    Ok(_) => {
+                        self.editing = None;
+                        self.message = None;
+                    }
+                    Err(e) => self.message = Some(e),
+                }
+            }
+            KeyCode::Esc => {
+                if edit == Edit::Filter {
+                    self.input.reset_cursor();
+                }
+                self.editing = None;
+                self.message = None;
+            }
+            _ => {}
+        }
+        self.follow_filter(edit);
+    }
+
+    /// Insert pasted text into the field being edited, it holds a single line.
+    fn paste(&mut self, edit: Edit, text: &str) {
+        self.candidates.clear();
+        self.input.paste(text, false);
+        self.follow_filter(edit);
+    }
+
+    /// The list follows the filter as it is typed.
+    fn follow_filter(&mut self, edit: Edit) {
+        if edit == Edit::Filter && self.filter != *self.input.get_string() {
+            self.filter = self.input.get_string().clone();
+            self.index = 0;
+        }
+    }
+
     fn down(&mut self) {
         if self.select {
             self.index += 1;
@@ -163,12 +431,22 @@ impl IndexPage {
         context: &mut AppContext,
         terminal: &mut Terminal<B>,
     ) -> Page {
-        context.path = self.port_list[0].port_name.clone();
+        if let Some(e) = context.error.take() {
+            self.message = Some(e);
+        }
         loop {
+            if context.path.is_empty() {
+                if let Some(port) = self.port_list.first() {
+                    context.path = port.port_name.clone();
+                }
+            }
             self.draw(context, terminal);
             if let Some(p) = self.event(context) {
                 return p;
             }
+            if self.scan_time.elapsed() >= RESCAN_INTERVAL {
+                self.rescan();
+            }
         }
     }
 
@@ -177,75 +455,77 @@ impl IndexPage {
     }
 
     fn event(&mut self, context: &mut AppContext) -> Option<Page> {
-        if let Ok(Event::Key(key)) = event::read() {
-            if key.kind == KeyEventKind::Press {
+        // wake up periodically so the port list can be refreshed
+        if !event::poll(Duration::from_millis(500)).unwrap_or(false) {
+            return None;
+        }
+        let event = event::read();
+        if let (Ok(Event::Paste(text)), Some(edit)) = (&event, self.editing) {
+            self.paste(edit, text);
+            return None;
+        }
+        if let Ok(Event::Key(key)) = event {
+            if key.kind != KeyEventKind::Press {
+                return None;
+            }
+            if let Some(edit) = self.editing {
+                self.edit(edit, &key, context);
+            } else {
                 match key.code {
-                    KeyCode::Enter => return Some(Page::Main),
+                    KeyCode::Enter => match check_path(&context.path) {
+                        Ok(_) => return Some(Page::Main),
+                        Err(e) => self.message = Some(e),
+                    },
                     KeyCode::Char('q') => return Some(Page::Exit),
-                    KeyCode::Down => self.down(),
-                    KeyCode::Up => self.up(),
-                    KeyCode::Char('0') => {
-                        if self.select {
-                            self.add_number(0)
-                        }
-                    }
-                    KeyCode::Char('1') => {
-                        if self.select {
-                            self.add_number(1)
-                        }
-                    }
-                    KeyCode::Char('2') => {
-                        if self.select {
-                            self.add_number(2)
-                        }
-                    }
-                    KeyCode::Char('3') => {
-                        if self.select {
-                            self.add_number(3)
-                        }
-                    }
-                    KeyCode::Char('4') => {
-                        if self.select {
-                            self.add_number(4)
-                        }
-                    }
-                    KeyCode::Char('5') => {
-                        if self.select {
-                            self.add_number(5)
-                        }
+                    KeyCode::Char('r') => self.rescan(),
+                    KeyCode::Char('p') => {
+                        self.select = false;
+                        self.position = Menu::Profile;
+                        self.editing = Some(Edit::Profile);
+                        self.input
+                            .set_string(context.profile.as_deref().unwrap_or_default());
                     }
-                    KeyCode::Char('6') => {
-                        if self.select {
-                            self.add_number(6)
-                        }
+                    KeyCode::Char('e') if self.position == Menu::SerialPort => {
+                        self.select = false;
+                        self.editing = Some(Edit::Path);
+                        self.input.set_string(&context.path);
                     }
-                    KeyCode::Char('7') => {
-                        if self.select {
-                            self.add_number(7)
-                        }
+                    KeyCode::Char('e') if self.position == Menu::BaudRate => {
+                        self.select = false;
+                        self.editing = Some(Edit::BaudRate);
+                        self.input.set_string(&context.baud_rate.to_string());
                     }
-                    KeyCode::Char('8') => {
-                        if self.select {
-                            self.add_number(8)
-                        }
+                    KeyCode::Char('f') if self.select && self.position == Menu::SerialPort => {
+                        self.editing = Some(Edit::Filter);
+                        self.input.set_string(&self.filter);
                     }
-                    KeyCode::Char('9') => {
-                        if self.select {
-                            self.add_number(9)
-                        }
+                    KeyCode::Char('s') if self.select && self.position == Menu::SerialPort => {
+                        self.sort_by = self.sort_by.next();
+                        self.index = 0;
                     }
-                    KeyCode::Backspace => {
-                        if self.select {
-                            self.delete_number()
-                        }
+                    KeyCode::Down => self.down(),
+                    KeyCode::Up => self.up(),
+                    KeyCode::Char(c @ '0'..='9') if self.select => {
+                        self.add_number(c.to_digit(10).unwrap() as usize)
                     }
+                    KeyCode::Backspace if self.select => self.delete_number(),
                     KeyCode::Right => self.select = true,
                     KeyCode::Left => {
                         self.select = false;
                         match self.position {
+                            Menu::Profile => {
+                                let name = context.config.profiles.keys().nth(self.index).cloned();
+                                if let Some(name) = name {
+                                    let result = context
+                                        .load_profile(&name)
+                                        .and_then(|_| context.use_profile(&name));
+                                    self.message = result.err();
+                                }
+                            }
+
                             Menu::SerialPort => {
-                                if self.index < self.port_list.len() {
-                                    context.path = self.port_list[self.index].port_name.clone()
+                                if let Some(port) = self.visible_ports().get(self.index) {
+                                    context.path = port.port_name.clone()
                                 }
                             }
 
@@ -312,17 +592,78 @@ impl IndexPage {
             )
             .fg(Color::White),
         );
+        line_list.push(Line::from("*Press [r] to rescan serial ports").fg(Color::White));
+        line_list.push(Line::from("*Press [p] to save the settings as a profile").fg(Color::White));
+        line_list.push(
+            Line::from("*Press [e] to type a port path or baud rate, [Tab] to complete a path")
+                .fg(Color::White),
+        );
         line_list.push(Line::from("*Press [Enter] to open Serial").fg(Color::Yellow));
         line_list.push(Line::from("*Press [q] to exit app").fg(Color::Red));
-        line_list.push(Line::from(""));
+        match &self.message {
+            Some(message) => line_list.push(Line::from(message.as_str()).fg(Color::Red)),
+            None => line_list.push(Line::from("")),
+        }
 
+        let menu_top = line_list.len() as u16;
         for menu in Menu::iter() {
             match menu {
+                _ if self.edit_menu() == Some(menu) => {
+                    let title = format!("<{menu}: ");
+                    f.set_cursor(
+                        menu_layout[1].x + (title.len() + self.input.get_index()) as u16,
+                        menu_layout[1].y + line_list.len() as u16,
+                    );
+                    line_list.push(Line::from(title + self.input.get_string()));
+                    for v in self.candidates.iter().take(MAX_CANDIDATES) {
+                        line_list.push(Line::from(format!("   {v}")));
+                    }
+                    if self.candidates.len() > MAX_CANDIDATES {
+                        let more = self.candidates.len() - MAX_CANDIDATES;
+                        line_list.push(Line::from(format!("   ... {more} more")));
+                    }
+                }
+                Menu::Profile => {
+                    let name = context.profile.as_deref().unwrap_or("-");
+                    line_list.push(Line::from(self.title(menu, name)));
+                    if self.position == menu && self.select {
+                        if context.config.profiles.is_empty() {
+                            line_list.push(Line::from("   (empty, press [p] to save one)"));
+                        }
+                        for (i, v) in context.config.profiles.keys().enumerate() {
+                            self.add_item(&mut line_list, i, v)
+                        }
+                    }
+                }
                 Menu::SerialPort => {
-                    line_list.push(Line::from(self.title(menu, &context.path)));
+                    if context.path.is_empty() {
+                        line_list.push(
+                            Line::from(self.title(menu, "no serial port found")).fg(Color::Red),
+                        );
+                    } else {
+                        line_list.push(Line::from(self.title(menu, &context.path)));
+                    }
                     if self.position == menu && self.select {
-                        for (i, v) in self.port_list.iter().enumerate() {
-                            self.add_item(&mut line_list, i, &v.port_name)
+                        let filter = format!("   Filter(f): {0}", self.filter);
+                        if self.editing == Some(Edit::Filter) {
+                            f.set_cursor(
+                                menu_layout[1].x
+                                    + (filter.len() - self.filter.len()) as u16
+                                    + self.input.get_index() as u16,
+                                menu_layout[1].y + line_list.len() as u16,
+                            );
+                        }
+                        line_list.push(
+                            Line::from(format!("{filter}  Sort(s): {0}", self.sort_by))
+                                .fg(Color::White),
+                        );
+                        if self.port_list.is_empty() {
+                            line_list.push(Line::from(
+                                "   (empty, plug in a device or press [r] to rescan)",
+                            ));
+                        }
+                        for (i, v) in self.visible_ports().iter().enumerate() {
+                            self.add_item(&mut line_list, i, &port_label(v))
                         }
                     }
                 }
@@ -374,5 +715,24 @@ impl IndexPage {
         // f.render_widget(Paragraph::new(logo()).centered(), layout[1]);
         let paragraph = Paragraph::new(line_list).left_aligned();
         f.render_widget(paragraph, menu_layout[1]);
+
+        let highlighted = if self.position == Menu::SerialPort && self.select {
+            self.visible_ports().get(self.index).copied()
+        } else {
+            self.port_list.iter().find(|v| v.port_name == context.path)
+        };
+        if let Some(port) = highlighted {
+            let detail = port_detail(port);
+            let area = menu_layout[0].intersection(Rect {
+                x: menu_layout[0].x + 1,
+                y: menu_layout[0].y + menu_top,
+                width: menu_layout[0].width.saturating_sub(2),
+                height: detail.len() as u16 + 2,
+            });
+            f.render_widget(
+                Paragraph::new(detail).block(Block::bordered().title("Port Info")),
+                area,
+            );
+        }
     }
 }
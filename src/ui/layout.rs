@@ -1,23 +1,52 @@
+use std::time::Instant;
+
 use ratatui::{
     backend::Backend,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    crossterm::event::{KeyCode, KeyEvent, KeyEventKind, MouseEvent},
     layout::{Constraint, Layout, Rect},
-    text::Line,
+    style::Stylize,
+    text::{Line, Span},
     widgets::{Paragraph, Tabs},
     Frame, Terminal,
 };
 use strum::IntoEnumIterator;
 
 use strum::{Display, EnumIter, FromRepr};
-use tokio_serial::SerialStream;
+use tokio::{
+    sync::mpsc::{self, Sender},
+    task::JoinHandle,
+    time::{self, interval, sleep_until, Duration},
+};
+
+use tokio_serial::{SerialPort, SerialPortInfo, SerialStream};
 
-use crate::ui::{Mode, Page};
+use crate::common::counter::{format_bytes, Counter};
+use crate::common::receive::Stamp;
+
+use crate::ui::{Action, AppContext, Link, Mode, Options, Page};
 
 use super::rxtx::RxTxWidget;
 
 pub trait MyWidget {
     fn event(&mut self, key: &KeyEvent);
-    fn input(&mut self, key: &KeyEvent, serial: &mut SerialStream);
+    /// Handle a key in input mode, returning the bytes to send, if any.
+    fn input(&mut self, key: &KeyEvent) -> Option<Vec<u8>>;
+    /// Called with the bytes read from the port and when they were read.
+    fn receive(&mut self, data: &[u8], stamp: Stamp);
+    /// Called with the bytes handed to the port for sending.
+    fn transmit(&mut self, data: &[u8]);
+    /// When `tick` wants to be called next, if at all.
+    fn deadline(&self) -> Option<Instant>;
+    fn tick(&mut self);
+    /// Called each time the port is opened.
+    fn connected(&mut self);
+    fn mouse(&mut self, event: &MouseEvent);
+    /// Handle text pasted in input mode.
+    fn paste(&mut self, text: &str);
+    /// Called when input mode is left with Esc.
+    fn leave_input(&mut self);
+    fn load_options(&mut self, options: &Options);
+    fn save_options(&self, options: &mut Options);
     fn build(&self, area: Rect, f: &mut Frame, mode: &Mode);
     fn state_list(&self) -> Vec<String>;
 }
@@ -59,10 +88,16 @@ impl SelectedTab {
 }
 
 pub struct MainLayout {
-    send_count: usize,
-    receive_count: usize,
+    send_count: Counter,
+    receive_count: Counter,
     selected_tab: SelectedTab,
     mode: Mode,
+    error: Option<String>,
+    /// Left by `connect` about the port settings, kept until the next connect.
+    warning: Option<String>,
+    port: String,
+    link: Option<Link>,
+    reconnect_task: Option<JoinHandle<()>>,
 
     widget: Box<dyn MyWidget>,
 }
@@ -70,116 +105,272 @@ pub struct MainLayout {
 impl Default for MainLayout {
     fn default() -> Self {
         Self {
-            send_count: Default::default(),
-            receive_count: Default::default(),
+            send_count: Counter::new(),
+            receive_count: Counter::new(),
             selected_tab: Default::default(),
             mode: Mode::Command,
+            error: None,
+            warning: None,
+            port: String::new(),
+            link: None,
+            reconnect_task: None,
             widget: Box::new(RxTxWidget::new()),
         }
     }
 }
 
 impl MainLayout {
-    pub fn run<B: Backend>(
+    pub async fn run<B: Backend>(
         &mut self,
         terminal: &mut Terminal<B>,
-        serial: &mut SerialStream,
+        context: &mut AppContext,
     ) -> Page {
-        loop {
-            self.draw(terminal, serial);
-            if let Some(page) = self.event(serial) {
-                return page;
+        context.options.char_time = context.char_time();
+        // history follows the port the page was opened for, even if it reconnects elsewhere
+        let history_port = context.path.clone();
+        context.load_history(&history_port);
+        self.widget.load_options(&context.options);
+        let (event_tx, mut event_rx) = mpsc::channel::<Action>(64);
+        let key_task = context.key_read(event_tx.clone());
+        let target = context.port_info();
+        self.port = context.path.clone();
+        match context.open() {
+            Ok(serial) => self.connect(context, serial, &event_tx),
+            // the driver rejected the settings, retrying will not help
+            Err(e) if e.kind() == tokio_serial::ErrorKind::InvalidInput => {
+                context.error = Some(format!(
+                    "open {0} at {1} baud failed: {2}",
+                    context.path, context.baud_rate, e
+                ));
+                key_task.abort();
+                return Page::Index;
+            }
+            Err(e) => {
+                self.error = Some(e.to_string());
+                self.reconnect_task = Some(context.reconnect(target.clone(), event_tx.clone()));
             }
         }
-    }
 
-    fn draw<B: Backend>(&self, terminal: &mut Terminal<B>, serial: &mut SerialStream) {
-        terminal.draw(|f| self.build(f, serial)).unwrap();
+        // refresh the rates even while the port is idle
+        let mut ticker = interval(Duration::from_millis(500));
+        let page = 'run: loop {
+            self.draw(terminal);
+            let deadline = self.widget.deadline().map(time::Instant::from_std);
+            let action = tokio::select! {
+                action = event_rx.recv() => action,
+                _ = sleep_until(deadline.unwrap_or_else(time::Instant::now)), if deadline.is_some() => {
+                    self.widget.tick();
+                    continue;
+                }
+                _ = ticker.tick() => {
+                    self.send_count.update();
+                    self.receive_count.update();
+                    continue;
+                }
+            };
+            let Some(mut action) = action else {
+                break Page::Exit;
+            };
+            // catch up on everything already queued so a fast port costs one redraw, not one per chunk
+            loop {
+                if let Some(page) = self.handle(action, context, &target, &event_tx) {
+                    break 'run page;
+                }
+                match event_rx.try_recv() {
+                    Ok(next) => action = next,
+                    Err(_) => break,
+                }
+            }
+        };
+
+        self.widget.save_options(&mut context.options);
+        if let Err(e) = context.save_history(&history_port) {
+            context.error = Some(e);
+        }
+        key_task.abort();
+        if let Some(task) = self.reconnect_task.take() {
+            task.abort();
+        }
+        self.link = None;
+        page
     }
 
-    fn event(&mut self, serial: &mut SerialStream) -> Option<Page> {
-        if let Ok(Event::Key(key)) = event::read() {
-            if key.kind == KeyEventKind::Press {
-                match self.mode {
-                    Mode::Command => match key.code {
-                        KeyCode::Esc => return Some(Page::Index),
-                        KeyCode::Char('q') => return Some(Page::Exit),
-                        KeyCode::Char('t') => self.selected_tab = SelectedTab::TxRx,
-                        KeyCode::Char('l') => self.selected_tab = SelectedTab::Command,
-                        KeyCode::Char('s') => self.selected_tab = SelectedTab::Stream,
-                        KeyCode::Char('y') => self.selected_tab = SelectedTab::Ymodem,
-                        KeyCode::Char('c') => self.selected_tab = SelectedTab::Chart,
-                        KeyCode::Char('i') => self.mode = Mode::Input,
-                        KeyCode::Right => self.selected_tab = self.selected_tab.next(),
-                        KeyCode::Left => self.selected_tab = self.selected_tab.previous(),
-                        _ => self.widget.event(&key),
-                    },
-                    Mode::Input => match key.code {
-                        KeyCode::Esc => self.mode = Mode::Command,
-                        _ => self.widget.input(&key, serial),
-                    },
+    /// Apply one action from the channel, returning the page to go to when it ends this one.
+    fn handle(
+        &mut self,
+        action: Action,
+        context: &mut AppContext,
+        target: &SerialPortInfo,
+        event_tx: &Sender<Action>,
+    ) -> Option<Page> {
+        match action {
+            Action::Input(key) => return self.event(&key),
+            Action::Mouse(mouse) => self.widget.mouse(&mouse),
+            Action::Paste(text) => {
+                if let Mode::Input = self.mode {
+                    self.widget.paste(&text);
                 }
             }
+            Action::Data(data, stamp) => {
+                self.receive_count.add(data.len());
+                self.widget.receive(&data, stamp)
+            }
+            Action::Sent(size) => {
+                self.send_count.add(size);
+                self.error = None;
+            }
+            Action::Error(e) => self.error = Some(e),
+            Action::Disconnected(e) => {
+                self.link = None;
+                self.error = Some(e);
+                self.reconnect_task = Some(context.reconnect(target.clone(), event_tx.clone()));
+            }
+            Action::Connected(serial, path) => {
+                context.path = path;
+                self.port = context.path.clone();
+                self.reconnect_task = None;
+                self.connect(context, serial, event_tx);
+            }
         }
         None
     }
 
-    fn read(&self, serial: &mut SerialStream) -> Option<Vec<u8>> {
-        let mut read_buf = vec![0; 4096];
-        if let Ok(size) = serial.try_read(read_buf.as_mut_slice()) {
-            return Some(read_buf[..size].to_vec());
+    fn connect(&mut self, context: &AppContext, serial: SerialStream, tx: &Sender<Action>) {
+        self.error = None;
+        // drivers may round a custom baud rate to the nearest one they support
+        self.warning = match serial.baud_rate() {
+            Ok(baud_rate) if baud_rate != context.baud_rate => Some(format!(
+                "baud rate {0} requested, driver set {1}",
+                context.baud_rate, baud_rate
+            )),
+            Ok(_) => None,
+            Err(e) => Some(e.to_string()),
+        };
+        self.link = Some(context.connect(serial, tx.clone()));
+        self.widget.connected();
+    }
+
+    fn draw<B: Backend>(&self, terminal: &mut Terminal<B>) {
+        terminal.draw(|f| self.build(f)).unwrap();
+    }
+
+    fn event(&mut self, key: &KeyEvent) -> Option<Page> {
+        if key.kind == KeyEventKind::Press {
+            match self.mode {
+                Mode::Command => match key.code {
+                    KeyCode::Esc => return Some(Page::Index),
+                    KeyCode::Char('q') => return Some(Page::Exit),
+                    KeyCode::Char('t') => self.selected_tab = SelectedTab::TxRx,
+                    KeyCode::Char('l') => self.selected_tab = SelectedTab::Command,
+                    KeyCode::Char('s') => self.selected_tab = SelectedTab::Stream,
+                    KeyCode::Char('y') => self.selected_tab = SelectedTab::Ymodem,
+                    KeyCode::Char('c') => self.selected_tab = SelectedTab::Chart,
+                    KeyCode::Char('i') => self.mode = Mode::Input,
+                    KeyCode::Char('r') => {
+                        self.send_count.reset();
+                        self.receive_count.reset();
+                    }
+                    KeyCode::Right => self.selected_tab = self.selected_tab.next(),
+                    KeyCode::Left => self.selected_tab = self.selected_tab.previous(),
+                    _ => self.widget.event(key),
+                },
+                Mode::Input => match key.code {
+                    KeyCode::Esc => {
+                        self.mode = Mode::Command;
+                        self.widget.leave_input();
+                    }
+                    _ => {
+                        if let Some(data) = self.widget.input(key) {
+                            match &self.link {
+                                Some(link) => match link.get_sender().try_send(data.clone()) {
+                                    Ok(()) => self.widget.transmit(&data),
+                                    Err(_) => self.error = Some(String::from("send queue full")),
+                                },
+                                None => self.error = Some(String::from("disconnected")),
+                            }
+                        }
+                    }
+                },
+            }
         }
-        return None;
+        None
     }
 
-    fn build(&self, f: &mut Frame, serial: &mut SerialStream) {
+    fn build(&self, f: &mut Frame) {
+        let mut status = vec![
+            Span::from(if self.link.is_some() {
+                self.port.clone()
+            } else {
+                format!("{0}(disconnected)", self.port)
+            }),
+            Span::from(format!(
+                "send:{0} {1}/s max:{2}/s",
+                self.send_count.get_total(),
+                format_bytes(self.send_count.get_rate()),
+                format_bytes(self.send_count.get_peak())
+            )),
+            Span::from(format!(
+                "receive:{0} {1}/s max:{2}/s",
+                self.receive_count.get_total(),
+                format_bytes(self.receive_count.get_rate()),
+                format_bytes(self.receive_count.get_peak())
+            )),
+        ];
+        if let Some(e) = &self.error {
+            status.push(Span::from(format!("error:{e}")).red());
+        }
+        if let Some(e) = &self.warning {
+            status.push(Span::from(format!("warning:{e}")).yellow());
+        }
+        // connection state first, the widget toggles below it, both wrapped rather than cut
+        let width = f.size().width as usize;
+        let mut status_lines = wrap_spans(status, width);
+        let states = self.widget.state_list().into_iter().map(|v| Span::from(v).dark_gray());
+        status_lines.extend(wrap_spans(states.collect(), width));
+
         let layout = Layout::vertical([
             Constraint::Length(1),
             Constraint::Fill(1),
-            Constraint::Length(1),
+            Constraint::Length(status_lines.len() as u16),
         ])
         .split(f.size());
 
+        let help = "[i] input mode | [r] reset count | [q] exit app | [Esc] back";
         let [tab_area, text_area] =
-            Layout::horizontal([Constraint::Percentage(80), Constraint::Percentage(20)])
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(help.len() as u16)])
                 .areas(layout[0]);
 
-        let state_layout = Layout::horizontal([
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-            Constraint::Percentage(10),
-        ])
-        .split(layout[2]);
-
         let titles = SelectedTab::iter().map(SelectedTab::title);
 
-        let mut state_tabs = vec![
-            String::from(format!("send:{0}", self.send_count)),
-            String::from(format!("receive:{0}", self.receive_count)),
-        ];
-
-        for v in self.widget.state_list().iter() {
-            state_tabs.push(v.clone());
-        }
-
         f.render_widget(
             Tabs::new(titles).select(self.selected_tab as usize),
             tab_area,
         );
-        f.render_widget(
-            Paragraph::new("[i] input mode | [q] exit app | [Esc] back"),
-            text_area,
-        );
+        f.render_widget(Paragraph::new(help), text_area);
         self.widget.build(layout[1], f, &self.mode);
-        for (i, v) in state_tabs.iter().enumerate() {
-            f.render_widget(Paragraph::new(v.clone()), state_layout[i]);
+        f.render_widget(Paragraph::new(status_lines), layout[2]);
+    }
+}
+
+/// Lay out status items two columns apart, starting a new line where the next one would not fit.
+fn wrap_spans(spans: Vec<Span<'static>>, width: usize) -> Vec<Line<'static>> {
+    let mut lines = vec![];
+    let mut line: Vec<Span> = vec![];
+    let mut used = 0;
+    for span in spans {
+        let gap = if line.is_empty() { 0 } else { 2 };
+        if !line.is_empty() && used + gap + span.width() > width {
+            lines.push(Line::from(std::mem::take(&mut line)));
+            used = 0;
+        } else if gap > 0 {
+            line.push(Span::from("  "));
+            used += gap;
         }
+        used += span.width();
+        line.push(span);
+    }
+    if !line.is_empty() {
+        lines.push(Line::from(line));
     }
+    lines
 }
@@ -1,10 +1,33 @@
-
-use std::{io::Read, time::Duration};
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+use futures::StreamExt;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt, ReadHalf, WriteHalf},
+    sync::mpsc::{self, Receiver, Sender},
+    task::JoinHandle,
+    time::interval,
+};
 use index::IndexPage;
 use layout::MainLayout;
-use ratatui::{backend::Backend, crossterm::event::KeyEvent, layout::Rect, Frame, Terminal};
-use tokio_serial::{DataBits, FlowControl, Parity, SerialPort, SerialPortBuilderExt, SerialStream, StopBits};
+use ratatui::{
+    backend::Backend,
+    crossterm::event::{Event, EventStream, KeyEvent, MouseEvent},
+    Terminal,
+};
+use tokio_serial::{
+    DataBits, FlowControl, Parity, SerialPortBuilder, SerialPortBuilderExt, SerialPortInfo,
+    SerialPortType, SerialStream, StopBits,
+};
+
+use crate::cli::Cli;
+use crate::common::decoder::{Encoding, Invalid};
+use crate::common::history::HistoryFile;
+use crate::common::line_ending::LineEnding;
+use crate::common::qa::{DEFAULT_IDLE, DEFAULT_TIMEOUT};
+use crate::common::receive::{Capacity, Delimiter, Stamp, Timestamp, DEFAULT_CAPACITY};
+use crate::common::config::{
+    data_bits_value, flow_control_name, parity_name, parse_data_bits, parse_flow_control,
+    parse_parity, parse_stop_bits, stop_bits_value, Config, Profile,
+};
 
 pub enum Mode {
     Command,
@@ -19,7 +42,54 @@ pub enum Page {
 
 pub enum Action{
     Input(KeyEvent),
-    Data(Vec<u8>)
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal.
+    Paste(String),
+    /// Bytes read from the port, with the time the read returned.
+    Data(Vec<u8>, Stamp),
+    /// Number of bytes the writer task put on the wire.
+    Sent(usize),
+    Error(String),
+    /// The port failed and has been closed.
+    Disconnected(String),
+    /// The port was reopened under the given path.
+    Connected(SerialStream, String)
+}
+
+/// The running reader and writer tasks of an open port.
+pub struct Link {
+    send_tx: Sender<Vec<u8>>,
+    tasks: [JoinHandle<()>; 2],
+}
+
+impl Link {
+    pub fn get_sender(&self) -> &Sender<Vec<u8>> {
+        &self.send_tx
+    }
+}
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+}
+
+/// Whether `port` is the same device as `target`, either by path or by USB identity.
+fn same_port(target: &SerialPortInfo, port: &SerialPortInfo) -> bool {
+    if target.port_name == port.port_name {
+        return true;
+    }
+    match (&target.port_type, &port.port_type) {
+        (SerialPortType::UsbPort(a), SerialPortType::UsbPort(b)) => {
+            a.serial_number.is_some()
+                && a.vid == b.vid
+                && a.pid == b.pid
+                && a.serial_number == b.serial_number
+        }
+        _ => false,
+    }
 }
 
 
@@ -27,6 +97,29 @@ pub mod index;
 pub mod layout;
 pub mod rxtx;
 
+/// Widget display and send settings kept across visits to the Main page.
+pub struct Options {
+    pub hex_mode: bool,
+    pub line_ending: LineEnding,
+    /// Decode C-style escapes in text sends.
+    pub escape: bool,
+    pub encoding: Encoding,
+    pub invalid: Invalid,
+    pub delimiter: Delimiter,
+    pub history: Capacity,
+    pub timestamp: Timestamp,
+    pub echo: bool,
+    pub qa_terminator: Option<Vec<u8>>,
+    pub qa_idle: Duration,
+    pub qa_timeout: Duration,
+    /// Silence that ends a frame in idle framing, `None` uses 3.5 character times.
+    pub frame_gap: Option<Duration>,
+    /// Time one character takes on the wire at the current settings.
+    pub char_time: Duration,
+    /// Sent lines of the current port, oldest first.
+    pub send_history: Vec<String>,
+}
+
 pub struct AppContext{
     path:String,
     baud_rate:u32,
@@ -34,7 +127,14 @@ pub struct AppContext{
     stop_bits:StopBits,
     parity:Parity,
     flow_control:FlowControl,
-    page:Page
+    page:Page,
+    /// Shown on the Index page after the Main page gave up on the port.
+    error:Option<String>,
+    options:Options,
+    config:Config,
+    history: HistoryFile,
+    /// Name of the profile the settings were loaded from or saved to.
+    profile:Option<String>
 }
 
 impl AppContext {
@@ -47,43 +147,338 @@ impl AppContext {
             stop_bits: StopBits::One, 
             parity: Parity::None, 
             flow_control: FlowControl::None,
-            page:Page::Index
+            page:Page::Index,
+            error:None,
+            options: Options {
+                hex_mode: false,
+                line_ending: LineEnding::None,
+                escape: false,
+                encoding: Encoding::Utf8,
+                invalid: Invalid::Replace,
+                delimiter: Delimiter::Lf,
+                history: DEFAULT_CAPACITY,
+                timestamp: Timestamp::Off,
+                echo: false,
+                qa_terminator: None,
+                qa_idle: DEFAULT_IDLE,
+                qa_timeout: DEFAULT_TIMEOUT,
+                frame_gap: None,
+                char_time: Duration::ZERO,
+                send_history: vec![],
+            },
+            config: Config::new(),
+            history: HistoryFile::new(),
+            profile: None
         }
     }
 
-    async fn serial_read(&self, serial_stream: SerialStream, tx_channel: Sender<Action>){
-        let tx = tx_channel.clone();
-        let serial_rx = serial_stream;
+    /// Read the config file and apply the last used profile.
+    pub fn load_config(&mut self) {
+        match Config::load() {
+            Ok(config) => {
+                self.config = config;
+                if let Some(name) = self.config.last_profile.clone() {
+                    if let Err(e) = self.load_profile(&name) {
+                        self.error = Some(e);
+                    }
+                }
+            }
+            Err(e) => self.error = Some(e),
+        }
+        match HistoryFile::load() {
+            Ok(history) => self.history = history,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Hand the send history of `port` to the widget options.
+    pub fn load_history(&mut self, port: &str) {
+        self.options.send_history = self.history.ports.get(port).cloned().unwrap_or_default();
+    }
+
+    /// Store the send history from the widget options under `port` and write it out.
+    pub fn save_history(&mut self, port: &str) -> Result<(), String> {
+        let entries = std::mem::take(&mut self.options.send_history);
+        if self.history.ports.get(port) == Some(&entries) {
+            return Ok(());
+        }
+        self.history.ports.insert(port.to_string(), entries);
+        self.history.save()
+    }
+
+    pub fn load_profile(&mut self, name: &str) -> Result<(), String> {
+        let Some(profile) = self.config.profiles.get(name).cloned() else {
+            return Err(format!("profile {name} not found"));
+        };
+        let ports = tokio_serial::available_ports().unwrap_or_default();
+        let same_device = |port: &SerialPortInfo| match &port.port_type {
+            SerialPortType::UsbPort(usb) => Some(usb.vid) == profile.vid && Some(usb.pid) == profile.pid,
+            _ => false,
+        };
+        // the saved path wins while it is still the same kind of device, only a serial number
+        // identifies one adapter well enough to follow it to another path
+        let saved = ports.iter().any(|v| v.port_name == profile.port && same_device(v));
+        self.path = if saved || profile.serial_number.is_none() {
+            profile.port
+        } else {
+            ports
+                .into_iter()
+                .find(|v| match &v.port_type {
+                    SerialPortType::UsbPort(usb) => same_device(v) && usb.serial_number == profile.serial_number,
+                    _ => false,
+                })
+                .map_or(profile.port, |v| v.port_name)
+        };
+        self.baud_rate = profile.baud_rate;
+        self.data_bits = parse_data_bits(profile.data_bits).unwrap_or(DataBits::Eight);
+        self.stop_bits = parse_stop_bits(profile.stop_bits).unwrap_or(StopBits::One);
+        self.parity = parse_parity(&profile.parity).unwrap_or(Parity::None);
+        self.flow_control = parse_flow_control(&profile.flow_control).unwrap_or(FlowControl::None);
+        self.options.hex_mode = profile.hex_mode;
+        self.options.line_ending = LineEnding::parse(&profile.line_ending).unwrap_or(LineEnding::None);
+        self.options.history = Capacity::parse(&profile.history).unwrap_or(DEFAULT_CAPACITY);
+        self.options.encoding = Encoding::parse(&profile.encoding).unwrap_or(Encoding::Utf8);
+        self.options.invalid = Invalid::parse(&profile.invalid).unwrap_or(Invalid::Replace);
+        self.options.delimiter = Delimiter::parse(&profile.delimiter).unwrap_or(Delimiter::Lf);
+        self.options.timestamp = Timestamp::parse(&profile.timestamp).unwrap_or(Timestamp::Off);
+        self.options.echo = profile.echo;
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Override the settings from the command line, opening the port right away when one is named.
+    pub fn apply_cli(&mut self, cli: &Cli) -> Result<(), String> {
+        if let Some(name) = &cli.profile {
+            self.load_profile(name)?;
+        }
+        if let Some(path) = &cli.port {
+            self.path = path.clone();
+        }
+        if let Some(baud_rate) = cli.baud_rate {
+            self.baud_rate = baud_rate;
+        }
+        if let Some(data_bits) = cli.data_bits {
+            self.data_bits = data_bits;
+        }
+        if let Some(stop_bits) = cli.stop_bits {
+            self.stop_bits = stop_bits;
+        }
+        if let Some(parity) = cli.parity {
+            self.parity = parity;
+        }
+        if let Some(flow_control) = cli.flow {
+            self.flow_control = flow_control;
+        }
+        if let Some(history) = cli.history {
+            self.options.history = history;
+        }
+        if let Some(idle) = cli.qa_idle {
+            self.options.qa_idle = Duration::from_millis(idle);
+        }
+        if let Some(timeout) = cli.qa_timeout {
+            self.options.qa_timeout = Duration::from_millis(timeout);
+        }
+        if let Some(gap) = cli.frame_gap {
+            self.options.frame_gap = Some(Duration::from_secs_f64(gap / 1000.0));
+        }
+        if cli.port.is_some() || cli.profile.is_some() {
+            self.page = Page::Main;
+        }
+        Ok(())
+    }
+
+    /// Store the current settings under `name` and make it the last used profile.
+    pub fn save_profile(&mut self, name: &str) -> Result<(), String> {
+        let mut profile = Profile {
+            port: self.path.clone(),
+            baud_rate: self.baud_rate,
+            data_bits: data_bits_value(self.data_bits),
+            stop_bits: stop_bits_value(self.stop_bits),
+            parity: parity_name(self.parity).to_string(),
+            flow_control: flow_control_name(self.flow_control).to_string(),
+            hex_mode: self.options.hex_mode,
+            line_ending: self.options.line_ending.name(),
+            history: self.options.history.to_string(),
+            encoding: self.options.encoding.name().to_string(),
+            invalid: self.options.invalid.name().to_string(),
+            delimiter: self.options.delimiter.name(),
+            timestamp: self.options.timestamp.name().to_string(),
+            echo: self.options.echo,
+            ..Default::default()
+        };
+        if let SerialPortType::UsbPort(usb) = self.port_info().port_type {
+            profile.vid = Some(usb.vid);
+            profile.pid = Some(usb.pid);
+            profile.serial_number = usb.serial_number;
+        }
+        self.config.profiles.insert(name.to_string(), profile);
+        self.use_profile(name)
+    }
+
+    /// Remember `name` as the profile to load on the next start.
+    pub fn use_profile(&mut self, name: &str) -> Result<(), String> {
+        self.profile = Some(name.to_string());
+        self.config.last_profile = Some(name.to_string());
+        self.config.save()
+    }
+
+    fn builder(&self) -> SerialPortBuilder {
+        tokio_serial::new(self.path.clone(), self.baud_rate)
+            .data_bits(self.data_bits)
+            .stop_bits(self.stop_bits)
+            .parity(self.parity)
+            .flow_control(self.flow_control)
+            .timeout(Duration::from_micros(1))
+    }
+
+    pub fn open(&self) -> tokio_serial::Result<SerialStream> {
+        self.builder().open_native_async()
+    }
+
+    /// Time one character takes on the wire: start bit, data bits, parity bit and stop bits.
+    pub fn char_time(&self) -> Duration {
+        let parity = match self.parity {
+            Parity::None => 0,
+            Parity::Odd | Parity::Even => 1,
+        };
+        let bits = 1 + data_bits_value(self.data_bits) + parity + stop_bits_value(self.stop_bits);
+        Duration::from_secs_f64(f64::from(bits) / f64::from(self.baud_rate.max(1)))
+    }
+
+    /// The enumerated entry for the current path, used to find the device again after it disappears.
+    pub fn port_info(&self) -> SerialPortInfo {
+        tokio_serial::available_ports()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|v| v.port_name == self.path)
+            .unwrap_or(SerialPortInfo {
+                port_name: self.path.clone(),
+                port_type: SerialPortType::Unknown,
+            })
+    }
+
+    /// Start the reader and writer tasks for an open port.
+    pub fn connect(&self, serial: SerialStream, tx: Sender<Action>) -> Link {
+        let (send_tx, send_rx) = mpsc::channel::<Vec<u8>>(64);
+        let (serial_rx, serial_tx) = tokio::io::split(serial);
+        let read_task = self.serial_read(serial_rx, tx.clone());
+        let write_task = self.serial_write(serial_tx, send_rx, tx);
+        Link {
+            send_tx,
+            tasks: [read_task, write_task],
+        }
+    }
+
+    /// Poll the system for `target` and send `Action::Connected` once it can be opened again.
+    pub fn reconnect(&self, target: SerialPortInfo, tx: Sender<Action>) -> JoinHandle<()> {
+        let builder = self.builder();
+        tokio::spawn(async move {
+            let mut ticker = interval(Duration::from_secs(1));
+            loop {
+                ticker.tick().await;
+                if tx.is_closed() {
+                    return;
+                }
+                // ptys and symlinks are never enumerated, so fall back to the original path
+                let path = tokio_serial::available_ports()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|v| same_port(&target, v))
+                    .map(|v| v.port_name)
+                    .unwrap_or(target.port_name.clone());
+                if let Ok(serial) = builder.clone().path(path.clone()).open_native_async() {
+                    let _ = tx.send(Action::Connected(serial, path)).await;
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Forward every chunk read from the port to the UI loop as `Action::Data`.
+    fn serial_read(&self, mut serial_rx: ReadHalf<SerialStream>, tx: Sender<Action>) -> JoinHandle<()> {
         tokio::spawn(async move {
-            if let x   = serial_rx.read(buf).unwrap(){
-                
+            let mut read_buf = vec![0; 4096];
+            loop {
+                match serial_rx.read(&mut read_buf).await {
+                    Ok(0) => {
+                        let _ = tx.send(Action::Disconnected(String::from("port closed"))).await;
+                        break;
+                    }
+                    Ok(size) => {
+                        let stamp = Stamp::now();
+                        if tx.send(Action::Data(read_buf[..size].to_vec(), stamp)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        let _ = tx.send(Action::Disconnected(e.to_string())).await;
+                        break;
+                    }
+                }
             }
-            tx
-        });
+        })
+    }
+
+    /// Write every buffer queued on `send_rx` to the port, reporting progress and failures as actions.
+    fn serial_write(&self, mut serial_tx: WriteHalf<SerialStream>, mut send_rx: Receiver<Vec<u8>>, tx: Sender<Action>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(data) = send_rx.recv().await {
+                let mut written = 0;
+                while written < data.len() {
+                    let action = match serial_tx.write(&data[written..]).await {
+                        Ok(0) => Action::Error(String::from("write zero")),
+                        Ok(size) => {
+                            written += size;
+                            Action::Sent(size)
+                        }
+                        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                        Err(e) => Action::Error(e.to_string()),
+                    };
+                    let failed = matches!(action, Action::Error(_));
+                    if tx.send(action).await.is_err() {
+                        return;
+                    }
+                    if failed {
+                        break;
+                    }
+                }
+                if let Err(e) = serial_tx.flush().await {
+                    if tx.send(Action::Error(e.to_string())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Forward terminal key events to the UI loop as `Action::Input`.
+    pub fn key_read(&self, tx: Sender<Action>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut reader = EventStream::new();
+            while let Some(Ok(event)) = reader.next().await {
+                let action = match event {
+                    Event::Key(key) => Action::Input(key),
+                    Event::Mouse(mouse) => Action::Mouse(mouse),
+                    Event::Paste(text) => Action::Paste(text),
+                    _ => continue,
+                };
+                if tx.send(action).await.is_err() {
+                    break;
+                }
+            }
+        })
     }
     
     pub async fn run_app<B: Backend>(&mut self, terminal:&mut Terminal<B>)->std::io::Result<()>{
         loop{
             match self.page {
                 Page::Index => {
-                    let serial_list = tokio_serial::available_ports().expect("not found port path");
-                    self.path = serial_list[0].port_name.clone();
+                    let serial_list = tokio_serial::available_ports().unwrap_or_default();
                     self.page = IndexPage::new(serial_list).run(self, terminal)
                 },
                 Page::Main => {
-                    let (event_tx, event_rx) = mpsc::channel::<Action>(64);
-                    let (send_tx, send_rx) = mpsc::channel::<Vec<u8>>(64);
-
-                    let mut serial = tokio_serial::new(self.path.clone(), self.baud_rate)
-                    .data_bits(self.data_bits)
-                    .stop_bits(self.stop_bits)
-                    .parity(self.parity)
-                    .flow_control(self.flow_control)
-                    .timeout(Duration::from_micros(1))
-                    .open_native_async()
-                    .expect(format!("open {} failed!", self.path).as_str());
-                    self.serial_read(serial, event_tx);
-                    self.page = MainLayout::default().run(terminal,&mut serial)
+                    self.page = MainLayout::default().run(terminal, self).await
                 },
                 Page::Exit => {
                     return Ok(());
@@ -91,4 +486,4 @@ impl AppContext {
             }
         }
     }
-}
\ No newline at end of file
+}
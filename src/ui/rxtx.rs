@@ -1,94 +1,653 @@
+use std::{
+    fs,
+    ops::Range,
+    time::{Duration, Instant},
+};
 
-use core::str;
-
+use chrono::Local;
+use unicode_width::UnicodeWidthStr;
 use ratatui::{
-    crossterm::event::{KeyCode, KeyEvent},
+    crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind},
     layout::{Constraint, Layout, Rect},
-    text::Line,
+    style::{Color, Stylize},
+    text::{Line, Span},
     widgets::{List, ListItem, Paragraph},
     Frame,
 };
-use tokio_serial::SerialStream;
 
-use crate::ui::Mode;
-use crate::common::input::Input;
+use crate::ui::{Mode, Options};
+use crate::common::decoder::{Decoder, Encoding, Invalid};
+use crate::common::escape::parse_escape;
+use crate::common::hex::{hex_string, hexdump_line, parse_hex, HEXDUMP_WIDTH};
+use crate::common::history::History;
+use crate::common::input::{paste_line, Input};
+use crate::common::line_ending::LineEnding;
+use crate::common::qa::{Outcome, QaSession, DEFAULT_IDLE, DEFAULT_TIMEOUT};
+use crate::common::receive::{Delimiter, Direction, ReceiveBuffer, Stamp, Timestamp};
+use crate::common::scroll::Scroll;
 
 use super::layout::MyWidget;
 
+/// Rows moved per mouse wheel step.
+const WHEEL_ROWS: usize = 3;
+
 pub struct RxTxWidget {
-    receive_buf: Vec<String>,
+    /// Received lines, rendered as text or hexdump.
+    receive_buf: ReceiveBuffer,
+    /// Last custom line delimiter, set from the input line with Ctrl-B.
+    custom_delimiter: Option<u8>,
+    /// Silence that ends a frame when splitting on idle gaps.
+    frame_gap: Duration,
+    scroll: Scroll,
+    timestamp: Timestamp,
+    /// Show sent frames between the received lines.
+    echo: bool,
+    /// Result of the last log export.
+    message: Option<String>,
+    /// Why the input line could not be sent, cleared by the next key.
+    send_error: Option<String>,
+    encoding: Encoding,
+    invalid: Invalid,
     input:Input,
+    history: History,
+    /// Query of the Ctrl-R search in progress.
+    search: Option<String>,
+    /// History entry the search currently matches.
+    found: Option<usize>,
     hex_mode: bool,
     qa_mode: bool,
-    enter_end:bool,
+    qa: QaSession,
+    line_ending: LineEnding,
+    /// Last custom line ending, set from the input line with Ctrl-N.
+    custom_ending: Vec<u8>,
+    escape: bool,
 }
 
 impl RxTxWidget {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
-            receive_buf: vec![],
+            receive_buf: ReceiveBuffer::new(),
+            custom_delimiter: None,
+            frame_gap: Duration::ZERO,
+            scroll: Scroll::new(),
+            timestamp: Timestamp::Off,
+            echo: false,
+            message: None,
+            send_error: None,
+            encoding: Encoding::Utf8,
+            invalid: Invalid::Replace,
             input: Input::new(),
+            history: History::new(),
+            search: None,
+            found: None,
             hex_mode: false,
             qa_mode: false,
-            enter_end:false,
+            qa: QaSession::new(DEFAULT_IDLE, DEFAULT_TIMEOUT),
+            line_ending: LineEnding::None,
+            custom_ending: vec![],
+            escape: false,
+        }
+    }
+
+    /// Rows the receive view shows in the current mode.
+    fn row_count(&self) -> usize {
+        if self.qa_mode {
+            self.qa.get_exchanges().len() * 2
+        } else if self.hex_mode {
+            self.receive_buf.byte_count().div_ceil(HEXDUMP_WIDTH)
+        } else {
+            self.receive_buf.line_count()
+        }
+    }
+
+    /// Rows received so far including the dropped ones, so eviction does not move the view.
+    fn row_end(&self) -> usize {
+        let (lines, bytes) = self.receive_buf.get_dropped();
+        if self.qa_mode {
+            self.row_count()
+        } else if self.hex_mode {
+            (bytes + self.receive_buf.byte_count()).div_ceil(HEXDUMP_WIDTH)
+        } else {
+            lines + self.receive_buf.line_count()
+        }
+    }
+
+    /// Handle the scrolling keys, shared by both modes.
+    fn scroll(&mut self, key: &KeyEvent) -> bool {
+        let total = self.row_count();
+        match key.code {
+            KeyCode::PageUp => self.scroll.page_up(total),
+            KeyCode::PageDown => self.scroll.page_down(),
+            KeyCode::Home => self.scroll.top(total),
+            KeyCode::End => self.scroll.bottom(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Bytes of a QA exchange as one row, line breaks shown as arrows.
+    fn show(&self, bytes: &[u8]) -> String {
+        if self.hex_mode {
+            return hex_string(bytes, " ");
+        }
+        let mut decoder = Decoder::new(self.encoding, self.invalid);
+        let mut text = decoder.decode(bytes);
+        text.push_str(&decoder.flush());
+        text.replace('\n', "\u{21b5}")
+            .chars()
+            .filter(|c| *c == '\t' || !c.is_control())
+            .collect()
+    }
+
+    fn qa_rows(&self, rows: Range<usize>) -> Vec<ListItem<'static>> {
+        let exchanges = self.qa.get_exchanges();
+        rows.map(|row| {
+            let exchange = &exchanges[row / 2];
+            if row % 2 == 0 {
+                let request = format!("#{0} > {1}", row / 2 + 1, self.show(exchange.get_request()));
+                return ListItem::new(Line::from(request).cyan());
+            }
+            let response = Span::from(format!("   < {0}", self.show(exchange.get_response())));
+            let ms = |v: Duration| v.as_millis();
+            let status = match exchange.get_outcome() {
+                Outcome::Pending => Span::from(" ...").yellow(),
+                Outcome::Terminator | Outcome::Idle => {
+                    Span::from(format!(" [{0} ms]", exchange.latency().map_or(0, ms))).green()
+                }
+                Outcome::Timeout => Span::from(format!(" TIMEOUT after {0} ms", ms(self.qa.get_timeout()))).red(),
+                Outcome::Interrupted => Span::from(" interrupted").yellow(),
+            };
+            ListItem::new(Line::from(vec![response, status]))
+        })
+        .collect()
+    }
+
+    /// Handle a key while a history search is in progress, `false` lets it through.
+    fn search(&mut self, key: &KeyEvent) -> bool {
+        let Some(query) = &mut self.search else {
+            return false;
+        };
+        let newest = self.history.get_entries().len();
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            // step to an older match
+            KeyCode::Char('r') if control => {
+                self.found = self.history.search(query, self.found.unwrap_or(newest)).or(self.found);
+            }
+            KeyCode::Char('g') if control => {
+                self.search = None;
+                self.found = None;
+            }
+            KeyCode::Char(c) if !control => {
+                query.push(c);
+                self.found = self.history.search(query, self.found.map_or(newest, |v| v + 1));
+            }
+            KeyCode::Backspace => {
+                query.pop();
+                self.found = self.history.search(query, newest);
+            }
+            // any other key takes the match and is handled as usual
+            _ => {
+                if let Some(i) = self.found {
+                    self.input.set_string(&self.history.get_entries()[i]);
+                }
+                self.search = None;
+                self.found = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Write the received lines to a file in the working directory.
+    fn export(&mut self) {
+        let path = Local::now().format("serial_tool_%Y%m%d_%H%M%S.log").to_string();
+        self.message = Some(match fs::write(&path, self.receive_buf.export(self.timestamp)) {
+            Ok(()) => path,
+            Err(e) => format!("{path}: {e}"),
+        });
+    }
+
+    /// The bytes the input line stands for in the current mode.
+    fn encode(&self) -> Result<Vec<u8>, String> {
+        let text = self.input.get_string();
+        if self.hex_mode {
+            let (bytes, errors) = parse_hex(text);
+            match errors.first() {
+                Some(i) => Err(format!("invalid hex at {i}")),
+                None => Ok(bytes),
+            }
+        } else if self.escape {
+            parse_escape(text).map_err(|(i, e)| format!("{e} at {i}"))
+        } else {
+            Ok(text.as_bytes().to_vec())
         }
     }
 }
 
 impl MyWidget for RxTxWidget {
     fn event(&mut self, key: &KeyEvent) {
+        if self.scroll(key) {
+            return;
+        }
         match key.code {
-            KeyCode::Char('h') => self.hex_mode = !self.hex_mode,
-            KeyCode::Char('a') => self.qa_mode = !self.qa_mode,
-            KeyCode::Char('n') => self.enter_end = !self.enter_end,
+            KeyCode::Char('h') => {
+                // rows differ between the views, start the other one at the tail
+                self.hex_mode = !self.hex_mode;
+                self.scroll.bottom();
+            }
+            KeyCode::Char('a') => {
+                self.qa_mode = !self.qa_mode;
+                self.scroll.bottom();
+            }
+            KeyCode::Char('n') => self.line_ending = self.line_ending.next(&self.custom_ending),
+            KeyCode::Char('e') => self.escape = !self.escape,
+            KeyCode::Char('b') => {
+                let delimiter = self.receive_buf.get_delimiter().next(self.custom_delimiter, self.frame_gap);
+                self.receive_buf.set_delimiter(delimiter);
+                self.scroll.bottom();
+            }
+            KeyCode::Char('o') => self.echo = !self.echo,
+            KeyCode::Char('m') => self.timestamp = self.timestamp.next(),
+            KeyCode::Char('w') => self.export(),
+            KeyCode::Char('d') => {
+                self.encoding = self.encoding.next();
+                self.receive_buf.set_decoding(self.encoding, self.invalid);
+            }
+            KeyCode::Char('x') => {
+                self.invalid = match self.invalid {
+                    Invalid::Replace => Invalid::Hex,
+                    Invalid::Hex => Invalid::Replace,
+                };
+                self.receive_buf.set_decoding(self.encoding, self.invalid);
+            }
             _ => {}
         }
     }
 
-    fn input(&mut self, key: &KeyEvent, serial:&mut SerialStream) {
-        let mut read_buf = vec![0;4096]; 
-        if let Ok(size) = serial.try_read(read_buf.as_mut_slice()){
-            if let Ok(str) = str::from_utf8(&read_buf[..size].to_vec())  {
-                self.receive_buf.push(str.to_string())
-            }
+    fn input(&mut self, key: &KeyEvent) -> Option<Vec<u8>> {
+        self.send_error = None;
+        if self.search(key) {
+            return None;
         }
-
-
         match key.code {
-            KeyCode::Char(c) => self.input.enter_char(c),
+            KeyCode::PageUp | KeyCode::PageDown => {
+                self.scroll(key);
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // the input line becomes the custom line ending
+                if let Ok(bytes) = self.encode() {
+                    if !bytes.is_empty() {
+                        self.custom_ending = bytes.clone();
+                        self.line_ending = LineEnding::Custom(bytes);
+                        self.input.reset_cursor();
+                    }
+                }
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // the input line becomes the QA response terminator, an empty one clears it
+                if let Ok(bytes) = self.encode() {
+                    self.qa.set_terminator((!bytes.is_empty()).then_some(bytes));
+                    self.input.reset_cursor();
+                }
+            }
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                // a single byte on the input line becomes the custom delimiter
+                if let Ok([byte]) = self.encode().as_deref() {
+                    self.custom_delimiter = Some(*byte);
+                    self.receive_buf.set_delimiter(Delimiter::Custom(*byte));
+                    self.scroll.bottom();
+                    self.input.reset_cursor();
+                }
+            }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.search = Some(String::new());
+            }
+            KeyCode::Up => {
+                if let Some(text) = self.history.previous(self.input.get_string()) {
+                    self.input.set_string(text);
+                }
+            }
+            KeyCode::Down => {
+                if let Some(text) = self.history.next() {
+                    self.input.set_string(text);
+                }
+            }
+            KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.move_cursor_home()
+            }
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.move_cursor_end()
+            }
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.kill_word_back()
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.kill_to_start()
+            }
+            KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.kill_to_end()
+            }
+            KeyCode::Char('y') if key.modifiers.contains(KeyModifiers::CONTROL) => self.input.yank(),
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.input.move_word_left()
+            }
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.input.move_word_right()
+            }
+            KeyCode::Char(c)
+                if !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                self.input.enter_char(c)
+            }
             KeyCode::Backspace => self.input.delete_char(),
+            KeyCode::Delete => self.input.delete_char_forward(),
+            KeyCode::Home => self.input.move_cursor_home(),
+            KeyCode::End => self.input.move_cursor_end(),
+            KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.move_word_left()
+            }
+            KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.move_word_right()
+            }
             KeyCode::Left => self.input.move_cursor_left(),
             KeyCode::Right => self.input.move_cursor_right(),
             KeyCode::Enter => {
-                serial.try_write(self.input.get_string().as_bytes()).unwrap();
+                let mut data = match self.encode() {
+                    Ok(data) => data,
+                    Err(e) => {
+                        self.send_error = Some(e);
+                        return None;
+                    }
+                };
+                data.extend_from_slice(self.line_ending.as_bytes());
+                self.history.push(self.input.get_string());
                 self.input.reset_cursor();
+                return Some(data);
             },
             _ => {}
         }
+        None
+    }
+
+    fn receive(&mut self, data: &[u8], stamp: Stamp) {
+        let rows = self.row_end();
+        self.receive_buf.push(data, stamp);
+        if self.qa_mode {
+            self.qa.receive(data, stamp.get_instant());
+        }
+        self.scroll.grow(self.row_end() - rows);
+    }
+
+    fn transmit(&mut self, data: &[u8]) {
+        let rows = self.row_end();
+        if self.echo {
+            self.receive_buf.transmit(data);
+        }
+        if self.qa_mode {
+            self.qa.request(data);
+        }
+        self.scroll.grow(self.row_end() - rows);
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.qa.deadline()
+    }
+
+    fn tick(&mut self) {
+        self.qa.tick();
+    }
+
+    fn connected(&mut self) {
+        self.receive_buf.connected();
+    }
+
+    fn paste(&mut self, text: &str) {
+        // an open Ctrl-R search takes the text as more of its query
+        if let Some(query) = &mut self.search {
+            query.push_str(&paste_line(text, true));
+            let newest = self.history.get_entries().len();
+            self.found = self.history.search(query, self.found.map_or(newest, |v| v + 1));
+            return;
+        }
+        self.input.paste(text, true);
+    }
+
+    fn leave_input(&mut self) {
+        // a search left open would swallow keys the next time input mode is entered
+        self.search = None;
+        self.found = None;
+    }
+
+    fn mouse(&mut self, event: &MouseEvent) {
+        match event.kind {
+            MouseEventKind::ScrollUp => self.scroll.up(WHEEL_ROWS, self.row_count()),
+            MouseEventKind::ScrollDown => self.scroll.down(WHEEL_ROWS),
+            _ => {}
+        }
+    }
+
+    fn load_options(&mut self, options: &Options) {
+        self.hex_mode = options.hex_mode;
+        self.line_ending = options.line_ending.clone();
+        if let LineEnding::Custom(bytes) = &self.line_ending {
+            self.custom_ending = bytes.clone();
+        }
+        self.escape = options.escape;
+        self.encoding = options.encoding;
+        self.invalid = options.invalid;
+        self.receive_buf.set_decoding(self.encoding, self.invalid);
+        // Modbus RTU style: 3.5 character times of silence between frames
+        self.frame_gap = options.frame_gap.unwrap_or(options.char_time.mul_f64(3.5));
+        self.receive_buf.set_char_time(options.char_time);
+        if let Delimiter::Custom(byte) = options.delimiter {
+            self.custom_delimiter = Some(byte);
+        }
+        self.receive_buf.set_delimiter(match options.delimiter {
+            Delimiter::Idle(_) => Delimiter::Idle(self.frame_gap),
+            ref delimiter => delimiter.clone(),
+        });
+        self.receive_buf.set_capacity(options.history);
+        self.timestamp = options.timestamp;
+        self.echo = options.echo;
+        self.history.set_entries(options.send_history.clone());
+        self.qa.set_terminator(options.qa_terminator.clone());
+        self.qa.set_timing(options.qa_idle, options.qa_timeout);
+    }
+
+    fn save_options(&self, options: &mut Options) {
+        options.hex_mode = self.hex_mode;
+        options.line_ending = self.line_ending.clone();
+        options.escape = self.escape;
+        options.encoding = self.encoding;
+        options.invalid = self.invalid;
+        options.delimiter = self.receive_buf.get_delimiter().clone();
+        options.timestamp = self.timestamp;
+        options.echo = self.echo;
+        options.send_history = self.history.get_entries().to_vec();
+        options.qa_terminator = self.qa.get_terminator().map(|v| v.to_vec());
     }
 
     fn build(&self, area: Rect, f: &mut Frame, mode: &Mode) {
 
-        let [text_area, send_area] =
-            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+        // escaped text gets a preview of the bytes it sends
+        let preview = self.escape && !self.hex_mode;
+        let [text_area, send_area, preview_area] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Length(preview as u16),
+        ])
+        .areas(area);
 
-        let list = self
-            .receive_buf
-            .iter()
-            .map(|v| ListItem::new(Line::from(v.as_str())));
+        // only the rows in view are built
+        let rows = self.scroll.window(self.row_count(), text_area.height as usize);
+        let list: Vec<ListItem> = if self.qa_mode {
+            self.qa_rows(rows.clone())
+        } else if self.hex_mode {
+            // offsets count the dropped bytes too
+            let (_, dropped) = self.receive_buf.get_dropped();
+            let start = rows.start * HEXDUMP_WIDTH;
+            self.receive_buf
+                .byte_window(start..rows.end * HEXDUMP_WIDTH)
+                .chunks(HEXDUMP_WIDTH)
+                .enumerate()
+                .map(|(i, v)| {
+                    let offset = dropped + start + i * HEXDUMP_WIDTH;
+                    ListItem::new(Line::from(hexdump_line(offset, v)))
+                })
+                .collect()
+        } else {
+            // frames split on silence are binary, each one is a hex row with its time and length
+            let framed = matches!(self.receive_buf.get_delimiter(), Delimiter::Idle(_));
+            let timestamp = match self.timestamp {
+                Timestamp::Off if framed => Timestamp::Wall,
+                timestamp => timestamp,
+            };
+            self.receive_buf
+                .window(rows.clone())
+                .map(|v| {
+                    let mut line = vec![Span::from(v.timestamp(timestamp)).dark_gray()];
+                    let marker = format!("{0} ", v.get_direction());
+                    let text = if framed {
+                        format!("[{0:>4}] {1}", v.get_bytes().len(), hex_string(v.get_bytes(), " "))
+                    } else {
+                        v.get_text().to_string()
+                    };
+                    match v.get_direction() {
+                        Direction::Tx(_) => {
+                            line.push(Span::from(marker).cyan());
+                            line.push(Span::from(text).cyan());
+                        }
+                        Direction::Rx => {
+                            if self.echo {
+                                line.push(Span::from(marker).green());
+                            }
+                            line.push(Span::from(text));
+                        }
+                    }
+                    ListItem::new(Line::from(line))
+                })
+                .collect()
+        };
         f.render_widget(List::new(list), text_area);
+        let (lines, bytes) = self.receive_buf.get_dropped();
+        if rows.start == 0 && bytes > 0 && !self.qa_mode && text_area.height > 0 {
+            let text = if self.hex_mode {
+                format!(" {bytes} older bytes dropped ")
+            } else {
+                format!(" {lines} older lines dropped ")
+            };
+            let width = (text.chars().count() as u16).min(text_area.width);
+            let notice = Rect::new(text_area.right() - width, text_area.y, width, 1);
+            f.render_widget(Paragraph::new(text).black().on_yellow(), notice);
+        }
+        if !self.scroll.is_following() && text_area.height > 0 {
+            let text = match self.scroll.get_unseen() {
+                0 => String::from(" [End] follow "),
+                n => format!(" {n} new lines [End] follow "),
+            };
+            let width = (text.chars().count() as u16).min(text_area.width);
+            let notice = Rect::new(
+                text_area.right() - width,
+                text_area.bottom() - 1,
+                width,
+                1,
+            );
+            f.render_widget(Paragraph::new(text).black().on_yellow(), notice);
+        }
+        let mut send_line = vec![];
+        let cursor;
+        if let Some(query) = &self.search {
+            // the match is shown until the search is left
+            let prompt = format!("(search)'{query}'");
+            cursor = prompt.width() - 1;
+            let found = self.found.map_or("", |i| self.history.get_entries()[i].as_str());
+            send_line.push(Span::from(prompt).yellow());
+            send_line.push(Span::from(": "));
+            send_line.push(Span::from(found));
+        } else {
+            // one column goes to the prompt
+            let view = self.input.view(send_area.width.saturating_sub(1) as usize);
+            cursor = 1 + usize::from(view.left) + view.cursor;
+            send_line.push(Span::from(">"));
+            if view.left {
+                send_line.push(Span::from("\u{ab}").dark_gray());
+            }
+            send_line.push(Span::from(" ".repeat(view.pad)));
+            let text: String = self
+                .input
+                .get_string()
+                .chars()
+                .skip(view.range.start)
+                .take(view.range.len())
+                .collect();
+            if self.hex_mode {
+                // mark the characters that keep the line from being sent
+                let (_, errors) = parse_hex(self.input.get_string());
+                for (i, c) in view.range.clone().zip(text.chars()) {
+                    let span = Span::from(c.to_string());
+                    send_line.push(if errors.binary_search(&i).is_ok() {
+                        span.fg(Color::Red).underlined()
+                    } else {
+                        span
+                    });
+                }
+            } else {
+                send_line.push(Span::from(text));
+            }
+            if view.right {
+                let used: usize = send_line.iter().map(|v| v.width()).sum();
+                let gap = (send_area.width as usize).saturating_sub(used + 1);
+                send_line.push(Span::from(" ".repeat(gap)));
+                send_line.push(Span::from("\u{bb}").dark_gray());
+            }
+        }
         match mode {
             Mode::Command => {}
-            Mode::Input => f.set_cursor(send_area.x + self.input.get_index() as u16 + 1, send_area.y),
+            Mode::Input => f.set_cursor(send_area.x + cursor as u16, send_area.y),
+        }
+        f.render_widget(Paragraph::new(Line::from(send_line)), send_area);
+        if preview {
+            let line = match self.encode() {
+                Ok(bytes) => Line::from(format!(" = {0} ({1} bytes)", hex_string(&bytes, " "), bytes.len())),
+                Err(e) => Line::from(format!(" ! {e}")).fg(Color::Red),
+            };
+            f.render_widget(Paragraph::new(line), preview_area);
         }
-        f.render_widget(Paragraph::new(format!(">{}", self.input.get_string())), send_area);
     }
 
     fn state_list(&self) -> Vec<String> {
-        return vec![
+        let mut list = vec![
             format!("[{0}]Hex Mode(h)", if self.hex_mode { "x" } else { " " }),
             format!("[{0}]QA Mode(a)", if self.qa_mode { "x" } else { " " }),
-            format!("[{0}]\\r\\n End(n)", if self.enter_end { "x" } else { " " }),
+            format!("End(n):{0}", self.line_ending),
         ];
+        if self.qa_mode {
+            let until = match self.qa.get_terminator() {
+                Some(bytes) => format!("[{0}]", hex_string(bytes, " ")),
+                None => String::from("idle"),
+            };
+            list.push(format!("Until(^T):{until}"));
+        }
+        list.extend([
+            format!("[{0}]Escape(e)", if self.escape { "x" } else { " " }),
+            format!("Split(b):{0}", self.receive_buf.get_delimiter()),
+            format!("Decode(d):{0}", self.encoding),
+            format!("Invalid(x):{0}", self.invalid),
+            // the hexdump only holds received bytes, sent frames come back with the line view
+            match (self.echo, self.hex_mode) {
+                (true, true) => String::from("[-]Echo(o):off in hex"),
+                (echo, _) => format!("[{0}]Echo(o)", if echo { "x" } else { " " }),
+            },
+            format!("Time(m):{0}", self.timestamp),
+            format!("History:{0}", self.receive_buf.get_capacity()),
+        ]);
+        if let Some(message) = &self.message {
+            list.push(format!("Log(w):{message}"));
+        }
+        if let Some(e) = &self.send_error {
+            list.push(format!("Not sent:{e}"));
+        }
+        list
     }
 }